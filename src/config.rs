@@ -0,0 +1,205 @@
+//! Snapshot of every writable register of the device.
+
+use crate::{Port, Register};
+
+/// The contents of all writable registers.
+///
+/// `Configuration::default()` holds the power-on reset values from the datasheet.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Configuration {
+    pub outputs: Port,
+    pub polarity: Port,
+    pub config: Port,
+    /// Output Drive Strength registers 0 (low byte) and 1 (high byte), two bits per pin.
+    pub output_drive: u16,
+    pub input_latch: Port,
+    pub pull_enable: Port,
+    pub pull_select: Port,
+    pub interrupt_mask: Port,
+    pub output_port_config: u8,
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Self {
+            outputs: Port::all(),
+            polarity: Port::empty(),
+            config: Port::all(),
+            output_drive: 0xFFFF,
+            input_latch: Port::empty(),
+            pull_enable: Port::empty(),
+            pull_select: Port::all(),
+            interrupt_mask: Port::all(),
+            output_port_config: 0x00,
+        }
+    }
+}
+
+impl Configuration {
+    /// Compare against `other` register by register.
+    pub fn diff(&self, other: &Configuration) -> ConfigurationDiff {
+        let [drive0, drive1] = self.output_drive.to_le_bytes();
+        let [other_drive0, other_drive1] = other.output_drive.to_le_bytes();
+        let pairs = [
+            (
+                Register::OUTPUT_PORT,
+                self.outputs.bits(),
+                other.outputs.bits(),
+            ),
+            (
+                Register::POLARITY_INVERSION,
+                self.polarity.bits(),
+                other.polarity.bits(),
+            ),
+            (
+                Register::CONFIG_PORT,
+                self.config.bits(),
+                other.config.bits(),
+            ),
+            (Register::OUTPUT_DRIVE_0, drive0, other_drive0),
+            (Register::OUTPUT_DRIVE_1, drive1, other_drive1),
+            (
+                Register::INPUT_LATCH,
+                self.input_latch.bits(),
+                other.input_latch.bits(),
+            ),
+            (
+                Register::PULLUPDOWN_EN,
+                self.pull_enable.bits(),
+                other.pull_enable.bits(),
+            ),
+            (
+                Register::PULLUPDOWN_SEL,
+                self.pull_select.bits(),
+                other.pull_select.bits(),
+            ),
+            (
+                Register::INTERRUPT_MASK,
+                self.interrupt_mask.bits(),
+                other.interrupt_mask.bits(),
+            ),
+            (
+                Register::OUTPUT_PORT_CONFIG,
+                self.output_port_config,
+                other.output_port_config,
+            ),
+        ];
+        ConfigurationDiff {
+            differences: RegisterDifference::collect(pairs),
+        }
+    }
+}
+
+/// A register whose value differs between two configurations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RegisterDifference {
+    pub register: Register,
+    /// The value in the configuration `diff()` was called on.
+    pub left: u8,
+    /// The value in the configuration passed to `diff()`.
+    pub right: u8,
+}
+
+impl RegisterDifference {
+    /// Keep the `(register, left, right)` pairs whose values differ, in order.  The capacity is
+    /// the number of pairs, so every difference fits.
+    pub(crate) fn collect<const N: usize>(
+        pairs: [(Register, u8, u8); N],
+    ) -> heapless::Vec<RegisterDifference, N> {
+        pairs
+            .into_iter()
+            .filter(|(_, left, right)| left != right)
+            .map(|(register, left, right)| RegisterDifference {
+                register,
+                left,
+                right,
+            })
+            .collect()
+    }
+
+    /// The bits that differ.
+    pub fn changed(&self) -> Port {
+        Port::from(self.left ^ self.right)
+    }
+}
+
+/// The registers that differ between two configurations, in register address order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigurationDiff {
+    differences: heapless::Vec<RegisterDifference, 10>,
+}
+
+impl ConfigurationDiff {
+    /// Whether the two configurations are identical.
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// The differing registers.
+    pub fn differences(&self) -> &[RegisterDifference] {
+        &self.differences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_hash_matches_eq() {
+        let mut a = Configuration::default();
+        let b = Configuration::default();
+        a.polarity = Port::P01;
+
+        let set: HashSet<Configuration> = [a, b, Configuration::default()].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let config = Configuration {
+            outputs: Port::P00 | Port::P07,
+            polarity: Port::P03,
+            config: Port::P04 | Port::P05,
+            output_drive: 0x1234,
+            input_latch: Port::P04,
+            pull_enable: Port::P05,
+            pull_select: Port::empty(),
+            interrupt_mask: Port::P00,
+            output_port_config: 0x01,
+        };
+
+        let json = serde_json::to_string(&config).unwrap();
+        let restored: Configuration = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, config);
+    }
+
+    #[test]
+    fn test_diff() {
+        let written = Configuration::default();
+        let mut read_back = written;
+        read_back.polarity = Port::P01;
+        read_back.config = Port::all() - Port::P00;
+
+        let diff = written.diff(&read_back);
+        assert_eq!(
+            diff.differences(),
+            [
+                RegisterDifference {
+                    register: Register::POLARITY_INVERSION,
+                    left: 0x00,
+                    right: 0x02,
+                },
+                RegisterDifference {
+                    register: Register::CONFIG_PORT,
+                    left: 0xFF,
+                    right: 0xFE,
+                },
+            ]
+        );
+        assert!(written.diff(&written).is_empty());
+    }
+}
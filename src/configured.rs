@@ -0,0 +1,108 @@
+//! Device handle that remembers the pin directions it configured.
+
+use crate::variant::Variant;
+use crate::{Error, Port, PCA9554};
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// Wraps a [`PCA9554`] and keeps a copy of the last Configuration register value written through
+/// it, so that output operations on input pins are rejected without a bus read.
+pub struct ConfiguredDevice<T, V> {
+    device: PCA9554<T, V>,
+    config: Port,
+}
+
+impl<T, E, V> ConfiguredDevice<T, V>
+where
+    T: WriteRead<Error = E> + Write<Error = E>,
+    V: Variant,
+{
+    /// Wrap `device`.  Until `write_config()` is called, all pins are assumed to be inputs,
+    /// which is the power-on default.
+    pub fn new(device: PCA9554<T, V>) -> Self {
+        Self {
+            device,
+            config: Port::all(),
+        }
+    }
+
+    /// Give back the device handle.
+    pub fn release(self) -> PCA9554<T, V> {
+        self.device
+    }
+
+    /// The pin directions last written.  Pins set to 1 are inputs, pins set to 0 are outputs.
+    pub fn config(&self) -> Port {
+        self.config
+    }
+
+    /// Set the pin directions and remember them.  Pins set to 1 are inputs, pins set to 0 are
+    /// outputs.
+    pub fn write_config(&mut self, i2c: &mut T, config: Port) -> Result<(), E> {
+        self.device.write_config(i2c, config)?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Drive `pins` high.  Returns `Error::NotAnOutput` without touching the bus if any of them
+    /// is configured as an input.
+    pub fn set_pin_high(&self, i2c: &mut T, pins: Port) -> Result<(), Error<E>> {
+        self.set_pins(i2c, pins, true)
+    }
+
+    /// Drive `pins` low.  Returns `Error::NotAnOutput` without touching the bus if any of them is
+    /// configured as an input.
+    pub fn set_pin_low(&self, i2c: &mut T, pins: Port) -> Result<(), Error<E>> {
+        self.set_pins(i2c, pins, false)
+    }
+
+    fn set_pins(&self, i2c: &mut T, pins: Port, high: bool) -> Result<(), Error<E>> {
+        if self.config.intersects(pins) {
+            return Err(Error::NotAnOutput);
+        }
+        self.device.modify_outputs(i2c, |mut outputs| {
+            outputs.set(pins, high);
+            outputs
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Address, Register};
+    use embedded_hal_mock::i2c::{Mock, Transaction};
+
+    #[test]
+    fn test_set_pin_high_on_input_is_rejected() {
+        let mut i2c = Mock::new(&[]);
+        let device = ConfiguredDevice::new(PCA9554::new(&i2c, Address::ADDR_0x20));
+        assert_eq!(
+            device.set_pin_high(&mut i2c, Port::P00),
+            Err(Error::NotAnOutput)
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_set_pin_high_on_output() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write(addr as u8, vec![Register::CONFIG_PORT as u8, 0b1111_1100]),
+            Transaction::write_read(addr as u8, vec![Register::OUTPUT_PORT as u8], vec![0x00]),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0000_0010]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let mut device = ConfiguredDevice::new(PCA9554::new(&i2c, addr));
+        device
+            .write_config(&mut i2c, Port::all() - Port::P00 - Port::P01)
+            .unwrap();
+        device.set_pin_high(&mut i2c, Port::P01).unwrap();
+        assert_eq!(
+            device.set_pin_high(&mut i2c, Port::P01 | Port::P02),
+            Err(Error::NotAnOutput)
+        );
+        i2c.done();
+    }
+}
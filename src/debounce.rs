@@ -0,0 +1,92 @@
+//! Software debouncing of input changes.
+
+use crate::variant::Variant;
+use crate::{Port, PCA9554};
+use embedded_hal::blocking::i2c::WriteRead;
+
+/// Filters interrupt chatter by only reporting a pin as changed once its new level has been read
+/// a number of times in a row.
+pub struct DebouncedInterrupts {
+    state: Port,
+    counters: [u8; 8],
+    threshold: u8,
+}
+
+impl DebouncedInterrupts {
+    /// Start from the settled state `initial`.  A pin is reported as changed after its new level
+    /// has been seen `threshold` consecutive times; a `threshold` of 0 is treated as 1.
+    pub fn new(initial: Port, threshold: u8) -> Self {
+        Self {
+            state: initial,
+            counters: [0; 8],
+            threshold: threshold.max(1),
+        }
+    }
+
+    /// The current settled state of all pins.
+    pub fn state(&self) -> Port {
+        self.state
+    }
+
+    /// Feed one input sample and return the pins whose settled state changed with it.
+    pub fn update(&mut self, sample: Port) -> Port {
+        let mut changed = Port::empty();
+        for (index, counter) in self.counters.iter_mut().enumerate() {
+            let pin = Port::from_bits_truncate(1 << index);
+            if sample.contains(pin) == self.state.contains(pin) {
+                *counter = 0;
+                continue;
+            }
+            *counter += 1;
+            if *counter >= self.threshold {
+                *counter = 0;
+                changed.insert(pin);
+            }
+        }
+        self.state.toggle(changed);
+        changed
+    }
+
+    /// Read the Input Port register of `device` and feed it to `update()`.
+    pub fn step<T, E, V>(&mut self, i2c: &mut T, device: &PCA9554<T, V>) -> Result<Port, E>
+    where
+        T: WriteRead<Error = E>,
+        V: Variant,
+    {
+        let sample = device.read_inputs(i2c)?;
+        Ok(self.update(sample))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Address, Register};
+    use embedded_hal_mock::i2c::{Mock, Transaction};
+
+    #[test]
+    fn test_flicker_then_settle() {
+        let addr = Address::ADDR_0x20;
+        let samples = [0x01, 0x00, 0x01, 0x00, 0x01, 0x01, 0x01, 0x01];
+        let expected: Vec<_> = samples
+            .iter()
+            .map(|&value| {
+                Transaction::write_read(addr as u8, vec![Register::INPUT_PORT as u8], vec![value])
+            })
+            .collect();
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let mut debounce = DebouncedInterrupts::new(Port::empty(), 3);
+        let changes: Vec<Port> = samples
+            .iter()
+            .map(|_| debounce.step(&mut i2c, &device).unwrap())
+            .collect();
+
+        assert_eq!(changes[..6], [Port::empty(); 6]);
+        assert_eq!(changes[6], Port::P00);
+        assert_eq!(changes[7], Port::empty());
+        assert_eq!(debounce.state(), Port::P00);
+        i2c.done();
+    }
+}
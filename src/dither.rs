@@ -0,0 +1,70 @@
+//! Crude LED brightness control by switching outputs on in only some frames.
+
+use crate::Port;
+
+/// For each brightness level, the frames of the four-frame cycle in which a pin is on.  The on
+/// frames are spread out to keep flicker low.
+const ON_FRAMES: [u8; 4] = [0b0000, 0b0001, 0b0101, 0b0111];
+
+/// Produces a repeating cycle of four output frames in which each pin is on in as many frames as
+/// its brightness level.
+///
+/// Write one frame per tick, e.g. with `PCA9554::write_outputs()`; the pins then average out to
+/// a duty cycle of `level / 4`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Dither {
+    frames: [Port; 4],
+    next: usize,
+}
+
+impl Dither {
+    /// `levels` holds the brightness of each pin, P00 first, from 0 (off) to 3.  Higher levels are
+    /// treated as 3.
+    pub fn new(levels: [u8; 8]) -> Self {
+        let mut frames = [Port::empty(); 4];
+        for (pin, &level) in levels.iter().enumerate() {
+            let on = ON_FRAMES[usize::from(level.min(3))];
+            for (index, frame) in frames.iter_mut().enumerate() {
+                frame.set(Port::from(1 << pin), on & (1 << index) != 0);
+            }
+        }
+        Self { frames, next: 0 }
+    }
+
+    /// The four frames of the cycle.
+    pub fn frames(&self) -> [Port; 4] {
+        self.frames
+    }
+
+    /// The next frame to write, cycling through the four frames.
+    pub fn next_frame(&mut self) -> Port {
+        let frame = self.frames[self.next];
+        self.next = (self.next + 1) % self.frames.len();
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_on_frames_match_level() {
+        let mut dither = Dither::new([0, 1, 2, 3, 2, 0, 0, 9]);
+        let frames: Vec<Port> = (0..8).map(|_| dither.next_frame()).collect();
+        assert_eq!(frames[..4], frames[4..]);
+
+        let on_count = |pin: Port| {
+            frames[..4]
+                .iter()
+                .filter(|frame| frame.contains(pin))
+                .count()
+        };
+        assert_eq!(on_count(Port::P00), 0);
+        assert_eq!(on_count(Port::P01), 1);
+        assert_eq!(on_count(Port::P02), 2);
+        assert_eq!(on_count(Port::P03), 3);
+        assert_eq!(on_count(Port::P04), 2);
+        assert_eq!(on_count(Port::P07), 3);
+    }
+}
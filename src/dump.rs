@@ -0,0 +1,115 @@
+//! Human readable dump of all device registers.
+
+use crate::{Port, Register, RegisterDifference};
+use core::fmt;
+
+/// The contents of every register, as read by `PCA9554::dump()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RegisterDump {
+    pub inputs: Port,
+    pub outputs: Port,
+    pub polarity: Port,
+    pub config: Port,
+    pub output_drive_0: u8,
+    pub output_drive_1: u8,
+    pub input_latch: Port,
+    pub pull_enable: Port,
+    pub pull_select: Port,
+    pub interrupt_mask: Port,
+    pub interrupt_status: Port,
+    pub output_port_config: u8,
+}
+
+/// The four core registers, as read by `PCA9554::io_snapshot()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IoSnapshot {
+    pub inputs: Port,
+    pub outputs: Port,
+    pub config: Port,
+    pub polarity: Port,
+}
+
+/// The registers that differ between two `IoSnapshot`s, in register address order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotDiff {
+    differences: heapless::Vec<RegisterDifference, 4>,
+}
+
+impl SnapshotDiff {
+    /// Compare `a` against `b` register by register.
+    pub fn between(a: &IoSnapshot, b: &IoSnapshot) -> SnapshotDiff {
+        let pairs = [
+            (Register::INPUT_PORT, a.inputs.bits(), b.inputs.bits()),
+            (Register::OUTPUT_PORT, a.outputs.bits(), b.outputs.bits()),
+            (
+                Register::POLARITY_INVERSION,
+                a.polarity.bits(),
+                b.polarity.bits(),
+            ),
+            (Register::CONFIG_PORT, a.config.bits(), b.config.bits()),
+        ];
+        SnapshotDiff {
+            differences: RegisterDifference::collect(pairs),
+        }
+    }
+
+    /// Whether the two snapshots are identical.
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// The differing registers.
+    pub fn differences(&self) -> &[RegisterDifference] {
+        &self.differences
+    }
+}
+
+impl fmt::Display for RegisterDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "in={:02X} out={:02X} pol={:02X} cfg={:02X} drv0={:02X} drv1={:02X} latch={:02X} \
+             puen={:02X} pusel={:02X} intmask={:02X} intstat={:02X} outcfg={:02X}",
+            self.inputs.bits(),
+            self.outputs.bits(),
+            self.polarity.bits(),
+            self.config.bits(),
+            self.output_drive_0,
+            self.output_drive_1,
+            self.input_latch.bits(),
+            self.pull_enable.bits(),
+            self.pull_select.bits(),
+            self.interrupt_mask.bits(),
+            self.interrupt_status.bits(),
+            self.output_port_config,
+        )
+    }
+}
+
+/// `fmt::Write` adapter over a byte buffer, failing once the buffer is full.
+pub(crate) struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    pub(crate) fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl fmt::Write for SliceWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
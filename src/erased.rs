@@ -0,0 +1,101 @@
+//! Type-erased output pins sharing one owned-bus device.
+
+use crate::{OwnedPCA9554, Port};
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::OutputPin;
+
+/// A single expander pin usable as an `embedded_hal` output pin.
+///
+/// All pins split from one device share it through an `Rc<RefCell<_>>`, so they must stay on one
+/// thread and must not be used re-entrantly.
+pub struct ErasedPin<T> {
+    device: Rc<RefCell<OwnedPCA9554<T>>>,
+    pin: Port,
+}
+
+impl<T, E> OutputPin for ErasedPin<T>
+where
+    T: WriteRead<Error = E> + Write<Error = E>,
+{
+    type Error = E;
+
+    fn set_low(&mut self) -> Result<(), E> {
+        self.device.borrow_mut().set_pin_low(self.pin)
+    }
+
+    fn set_high(&mut self) -> Result<(), E> {
+        self.device.borrow_mut().set_pin_high(self.pin)
+    }
+}
+
+impl<T, E> OwnedPCA9554<T>
+where
+    T: WriteRead<Error = E> + Write<Error = E> + 'static,
+    E: 'static,
+{
+    /// Split the device into eight boxed output pins, P00 first.
+    ///
+    /// Shadow mode is turned off, as nothing could call `commit()` once the device is shared
+    /// by the pins.  Changes still pending in the shadow are discarded, call `commit()` before
+    /// splitting to keep them.  The pins are not reconfigured as outputs, use `write_config()`
+    /// before splitting.
+    pub fn into_erased_output_pins(mut self) -> [Box<dyn OutputPin<Error = E>>; 8] {
+        self.set_shadow(false);
+        let device = Rc::new(RefCell::new(self));
+        core::array::from_fn(|index| {
+            Box::new(ErasedPin {
+                device: device.clone(),
+                pin: Port::from_bits_truncate(1 << index),
+            }) as Box<dyn OutputPin<Error = E>>
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Address, Register};
+    use embedded_hal_mock::i2c::{Mock, Transaction};
+
+    #[test]
+    fn test_boxed_pin_set_high() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::OUTPUT_PORT as u8],
+                vec![0b0000_0000],
+            ),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0000_1000]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = OwnedPCA9554::new(i2c.clone(), addr);
+        let mut pins = device.into_erased_output_pins();
+        pins[3].set_high().unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_split_leaves_shadow_mode() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::OUTPUT_PORT as u8],
+                vec![0b0000_0000],
+            ),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0000_0001]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let mut device = OwnedPCA9554::new(i2c.clone(), addr);
+        device.set_shadow(true);
+        let mut pins = device.into_erased_output_pins();
+        pins[0].set_high().unwrap();
+        i2c.done();
+    }
+}
@@ -0,0 +1,155 @@
+//! Trait abstracting over the core I/O expander operations.
+//!
+//! Application code written against [`Expander`] can be driven by a [`PCA9554`] on real hardware
+//! and by an in-memory fake in tests.
+//!
+//! The trait is dyn-compatible, so expanders sharing a bus type can be stored as
+//! `&mut dyn Expander<I2C, Error = E>` or `Box<dyn Expander<I2C, Error = E>>`.  Keep new methods
+//! free of generic parameters to preserve this.
+
+use crate::variant::Variant;
+use crate::{Port, PCA9554};
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// Core operations of an 8-bit I/O expander on a bus of type `I2C`.
+pub trait Expander<I2C> {
+    type Error;
+
+    /// Read the logic levels of all pins.
+    fn read_inputs(&mut self, i2c: &mut I2C) -> Result<Port, Self::Error>;
+
+    /// Read the output state of all pins.
+    fn read_outputs(&mut self, i2c: &mut I2C) -> Result<Port, Self::Error>;
+
+    /// Set the output state of all pins.
+    fn write_outputs(&mut self, i2c: &mut I2C, output: Port) -> Result<(), Self::Error>;
+
+    /// Read the pin directions.  Pins set to 1 are inputs, pins set to 0 are outputs.
+    fn read_config(&mut self, i2c: &mut I2C) -> Result<Port, Self::Error>;
+
+    /// Set the pin directions.  Pins set to 1 are inputs, pins set to 0 are outputs.
+    fn write_config(&mut self, i2c: &mut I2C, config: Port) -> Result<(), Self::Error>;
+}
+
+impl<T, E, V> Expander<T> for PCA9554<T, V>
+where
+    T: WriteRead<Error = E> + Write<Error = E>,
+    V: Variant,
+{
+    type Error = E;
+
+    fn read_inputs(&mut self, i2c: &mut T) -> Result<Port, E> {
+        PCA9554::read_inputs(self, i2c)
+    }
+
+    fn read_outputs(&mut self, i2c: &mut T) -> Result<Port, E> {
+        PCA9554::read_outputs(self, i2c)
+    }
+
+    fn write_outputs(&mut self, i2c: &mut T, output: Port) -> Result<(), E> {
+        PCA9554::write_outputs(self, i2c, output)
+    }
+
+    fn read_config(&mut self, i2c: &mut T) -> Result<Port, E> {
+        PCA9554::read_config(self, i2c)
+    }
+
+    fn write_config(&mut self, i2c: &mut T, config: Port) -> Result<(), E> {
+        PCA9554::write_config(self, i2c, config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Address, Register};
+    use core::convert::Infallible;
+    use embedded_hal_mock::i2c::{Mock, Transaction};
+    use embedded_hal_mock::MockError;
+
+    /// In-memory expander whose inputs mirror its outputs.
+    struct Fake {
+        outputs: Port,
+        config: Port,
+    }
+
+    impl Expander<()> for Fake {
+        type Error = Infallible;
+
+        fn read_inputs(&mut self, _: &mut ()) -> Result<Port, Infallible> {
+            Ok(self.outputs)
+        }
+
+        fn read_outputs(&mut self, _: &mut ()) -> Result<Port, Infallible> {
+            Ok(self.outputs)
+        }
+
+        fn write_outputs(&mut self, _: &mut (), output: Port) -> Result<(), Infallible> {
+            self.outputs = output;
+            Ok(())
+        }
+
+        fn read_config(&mut self, _: &mut ()) -> Result<Port, Infallible> {
+            Ok(self.config)
+        }
+
+        fn write_config(&mut self, _: &mut (), config: Port) -> Result<(), Infallible> {
+            self.config = config;
+            Ok(())
+        }
+    }
+
+    /// Application logic under test: make P00 an output and copy the P07 input to it.
+    fn follow<X: Expander<I>, I>(expander: &mut X, i2c: &mut I) -> Result<(), X::Error> {
+        expander.write_config(i2c, Port::all() - Port::P00)?;
+        let inputs = expander.read_inputs(i2c)?;
+        let mut outputs = expander.read_outputs(i2c)?;
+        outputs.set(Port::P00, inputs.contains(Port::P07));
+        expander.write_outputs(i2c, outputs)
+    }
+
+    #[test]
+    fn test_generic_code_with_fake() {
+        let mut fake = Fake {
+            outputs: Port::P07,
+            config: Port::all(),
+        };
+        follow(&mut fake, &mut ()).unwrap();
+        assert_eq!(fake.outputs, Port::P00 | Port::P07);
+        assert_eq!(fake.config, Port::all() - Port::P00);
+    }
+
+    #[test]
+    fn test_generic_code_with_device() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write(addr as u8, vec![Register::CONFIG_PORT as u8, 0b1111_1110]),
+            Transaction::write_read(addr as u8, vec![Register::INPUT_PORT as u8], vec![0x80]),
+            Transaction::write_read(addr as u8, vec![Register::OUTPUT_PORT as u8], vec![0x00]),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0000_0001]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let mut device = PCA9554::new(&i2c, addr);
+        follow(&mut device, &mut i2c).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_boxed_trait_object() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(addr as u8, vec![Register::INPUT_PORT as u8], vec![0x81]),
+            Transaction::write_read(addr as u8, vec![Register::OUTPUT_PORT as u8], vec![0x0F]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let mut boxed: Box<dyn Expander<Mock, Error = MockError>> =
+            Box::new(PCA9554::new(&i2c, addr));
+        assert_eq!(boxed.read_inputs(&mut i2c).unwrap(), Port::P00 | Port::P07);
+
+        let borrowed: &mut dyn Expander<Mock, Error = MockError> = boxed.as_mut();
+        assert_eq!(borrowed.read_outputs(&mut i2c).unwrap(), Port::from(0x0F));
+        i2c.done();
+    }
+}
@@ -0,0 +1,107 @@
+//! Holding all outputs off until the application is ready to drive them.
+
+use crate::variant::Variant;
+use crate::{Port, PCA9554};
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// Keeps every pin an input, i.e. high-impedance, until `enable()` is called.
+///
+/// Before `enable()`, `write_outputs()`, `set_pin()` and `write_config()` only update an
+/// in-memory copy of the Output Port and Configuration registers.  `enable()` writes that copy to
+/// the device and afterwards the same calls go straight to the device.
+pub struct OutputGate<T, V> {
+    device: PCA9554<T, V>,
+    outputs: Port,
+    config: Port,
+    enabled: bool,
+}
+
+impl<T, E, V> OutputGate<T, V>
+where
+    T: WriteRead<Error = E> + Write<Error = E>,
+    V: Variant,
+{
+    /// Configure every pin of `device` as an input and start staging.
+    ///
+    /// The staged registers start out at their power-on values: all outputs high, all pins
+    /// inputs.
+    pub fn new(i2c: &mut T, device: PCA9554<T, V>) -> Result<Self, E> {
+        device.write_config(i2c, Port::all())?;
+        Ok(Self {
+            device,
+            outputs: Port::all(),
+            config: Port::all(),
+            enabled: false,
+        })
+    }
+
+    /// Whether `enable()` has been called.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Write the staged Output Port value, then the staged Configuration, and stop staging.
+    pub fn enable(&mut self, i2c: &mut T) -> Result<(), E> {
+        self.device.write_outputs(i2c, self.outputs)?;
+        self.device.write_config(i2c, self.config)?;
+        self.enabled = true;
+        Ok(())
+    }
+
+    /// Set the output state of all pins.
+    pub fn write_outputs(&mut self, i2c: &mut T, outputs: Port) -> Result<(), E> {
+        if self.enabled {
+            self.device.write_outputs(i2c, outputs)?;
+        }
+        self.outputs = outputs;
+        Ok(())
+    }
+
+    /// Drive `pin` high or low.  The other outputs are left unchanged.
+    pub fn set_pin(&mut self, i2c: &mut T, pin: Port, high: bool) -> Result<(), E> {
+        let mut outputs = self.outputs;
+        outputs.set(pin, high);
+        self.write_outputs(i2c, outputs)
+    }
+
+    /// Set the pin directions.  Pins set to 1 are inputs, pins set to 0 are outputs.
+    pub fn write_config(&mut self, i2c: &mut T, config: Port) -> Result<(), E> {
+        if self.enabled {
+            self.device.write_config(i2c, config)?;
+        }
+        self.config = config;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Address, Register};
+    use embedded_hal_mock::i2c::{Mock, Transaction};
+
+    #[test]
+    fn test_nothing_driven_before_enable() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write(addr as u8, vec![Register::CONFIG_PORT as u8, 0xFF]),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0000_0010]),
+            Transaction::write(addr as u8, vec![Register::CONFIG_PORT as u8, 0b1111_1100]),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0000_0011]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let mut gate = OutputGate::new(&mut i2c, device).unwrap();
+        gate.write_outputs(&mut i2c, Port::empty()).unwrap();
+        gate.set_pin(&mut i2c, Port::P01, true).unwrap();
+        gate.write_config(&mut i2c, Port::all() - Port::P00 - Port::P01)
+            .unwrap();
+        assert!(!gate.is_enabled());
+
+        gate.enable(&mut i2c).unwrap();
+        assert!(gate.is_enabled());
+        gate.set_pin(&mut i2c, Port::P00, true).unwrap();
+        i2c.done();
+    }
+}
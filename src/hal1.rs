@@ -0,0 +1,67 @@
+//! Helpers for buses implementing the embedded-hal 1.0 `I2c` trait.
+//!
+//! Unlike embedded-hal 0.2, bus errors in 1.0 carry an [`ErrorKind`], which lets a missing
+//! device, reported as a NACK, be told apart from a real bus fault.
+
+use crate::{Address, Register};
+use embedded_hal_1::i2c::{Error as _, ErrorKind, I2c};
+
+/// Check whether a device answers at `address`, with a single read of the register used by
+/// [`PCA9554::probe_with_retry`](crate::PCA9554::probe_with_retry).
+///
+/// Returns `Ok(false)` if the bus reports a NACK.  Any other error, e.g. an arbitration loss, is
+/// returned and can be inspected with [`embedded_hal_1::i2c::Error::kind()`].
+pub fn probe<I: I2c>(i2c: &mut I, address: Address) -> Result<bool, I::Error> {
+    let mut buffer = [0u8; 1];
+    match i2c.write_read(address as u8, &[Register::CONFIG_PORT as u8], &mut buffer) {
+        Ok(()) => Ok(true),
+        Err(error) if matches!(error.kind(), ErrorKind::NoAcknowledge(_)) => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal_1::i2c::{ErrorType, NoAcknowledgeSource, Operation};
+
+    #[derive(Debug, PartialEq)]
+    struct FakeError(ErrorKind);
+
+    impl embedded_hal_1::i2c::Error for FakeError {
+        fn kind(&self) -> ErrorKind {
+            self.0
+        }
+    }
+
+    /// A bus failing every transaction with the given kind, or succeeding if there is none.
+    struct FakeBus(Option<ErrorKind>);
+
+    impl ErrorType for FakeBus {
+        type Error = FakeError;
+    }
+
+    impl I2c for FakeBus {
+        fn transaction(&mut self, _: u8, _: &mut [Operation<'_>]) -> Result<(), FakeError> {
+            match self.0 {
+                Some(kind) => Err(FakeError(kind)),
+                None => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_probe() {
+        let addr = Address::ADDR_0x20;
+        assert_eq!(probe(&mut FakeBus(None), addr), Ok(true));
+
+        let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+        assert_eq!(probe(&mut FakeBus(Some(nack)), addr), Ok(false));
+
+        let bus = ErrorKind::Bus;
+        assert_eq!(
+            probe(&mut FakeBus(Some(bus)), addr),
+            Err(FakeError(ErrorKind::Bus))
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Bus health statistics.
+
+use crate::variant::Variant;
+use crate::{Port, PCA9554};
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// Wraps a [`PCA9554`] and counts the successful and failed transfers made through it.
+pub struct HealthMonitor<T, V> {
+    device: PCA9554<T, V>,
+    successes: u32,
+    errors: u32,
+    consecutive_errors: u32,
+}
+
+impl<T, E, V> HealthMonitor<T, V>
+where
+    T: WriteRead<Error = E> + Write<Error = E>,
+    V: Variant,
+{
+    /// Wrap `device`, starting with all counts at zero.
+    pub fn new(device: PCA9554<T, V>) -> Self {
+        Self {
+            device,
+            successes: 0,
+            errors: 0,
+            consecutive_errors: 0,
+        }
+    }
+
+    /// Give back the device handle.
+    pub fn release(self) -> PCA9554<T, V> {
+        self.device
+    }
+
+    /// The number of calls that succeeded.
+    pub fn successes(&self) -> u32 {
+        self.successes
+    }
+
+    /// The number of calls that failed.
+    pub fn errors(&self) -> u32 {
+        self.errors
+    }
+
+    /// The number of calls that failed since the last successful one.
+    pub fn consecutive_errors(&self) -> u32 {
+        self.consecutive_errors
+    }
+
+    /// The fraction of calls that failed, from 0.0 to 1.0.  This is 0.0 before the first call.
+    pub fn error_rate(&self) -> f32 {
+        let total = self.successes as f32 + self.errors as f32;
+        if total == 0.0 {
+            0.0
+        } else {
+            self.errors as f32 / total
+        }
+    }
+
+    /// Forget all counts.
+    pub fn reset(&mut self) {
+        self.successes = 0;
+        self.errors = 0;
+        self.consecutive_errors = 0;
+    }
+
+    /// See [`PCA9554::read_inputs`].
+    pub fn read_inputs(&mut self, i2c: &mut T) -> Result<Port, E> {
+        let result = self.device.read_inputs(i2c);
+        self.track(result)
+    }
+
+    /// See [`PCA9554::read_outputs`].
+    pub fn read_outputs(&mut self, i2c: &mut T) -> Result<Port, E> {
+        let result = self.device.read_outputs(i2c);
+        self.track(result)
+    }
+
+    /// See [`PCA9554::write_outputs`].
+    pub fn write_outputs(&mut self, i2c: &mut T, output: Port) -> Result<(), E> {
+        let result = self.device.write_outputs(i2c, output);
+        self.track(result)
+    }
+
+    /// See [`PCA9554::read_config`].
+    pub fn read_config(&mut self, i2c: &mut T) -> Result<Port, E> {
+        let result = self.device.read_config(i2c);
+        self.track(result)
+    }
+
+    /// See [`PCA9554::write_config`].
+    pub fn write_config(&mut self, i2c: &mut T, config: Port) -> Result<(), E> {
+        let result = self.device.write_config(i2c, config);
+        self.track(result)
+    }
+
+    fn track<R>(&mut self, result: Result<R, E>) -> Result<R, E> {
+        if result.is_ok() {
+            self.successes = self.successes.saturating_add(1);
+            self.consecutive_errors = 0;
+        } else {
+            self.errors = self.errors.saturating_add(1);
+            self.consecutive_errors = self.consecutive_errors.saturating_add(1);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Address, Register};
+    use embedded_hal_mock::i2c::{Mock, Transaction};
+    use embedded_hal_mock::MockError;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn test_counts_and_error_rate() {
+        let addr = Address::ADDR_0x20;
+        let read = |value| {
+            Transaction::write_read(addr as u8, vec![Register::INPUT_PORT as u8], vec![value])
+        };
+        let failed = || read(0).with_error(MockError::Io(ErrorKind::Other));
+        let expected = [read(0x01), failed(), failed(), read(0x02), failed()];
+
+        let mut i2c = Mock::new(&expected);
+        let mut monitor = HealthMonitor::new(PCA9554::new(&i2c, addr));
+        assert_eq!(monitor.error_rate(), 0.0);
+
+        assert!(monitor.read_inputs(&mut i2c).is_ok());
+        assert!(monitor.read_inputs(&mut i2c).is_err());
+        assert!(monitor.read_inputs(&mut i2c).is_err());
+        assert_eq!(monitor.consecutive_errors(), 2);
+        assert!(monitor.read_inputs(&mut i2c).is_ok());
+        assert_eq!(monitor.consecutive_errors(), 0);
+        assert!(monitor.read_inputs(&mut i2c).is_err());
+
+        assert_eq!(monitor.successes(), 2);
+        assert_eq!(monitor.errors(), 3);
+        assert_eq!(monitor.consecutive_errors(), 1);
+        assert_eq!(monitor.error_rate(), 0.6);
+        i2c.done();
+    }
+}
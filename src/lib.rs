@@ -5,9 +5,54 @@
 // Tests require std for mocking the i2c bus
 #![cfg_attr(not(test), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 use core::convert::TryFrom;
+use core::fmt::{self, Write as _};
 use core::marker::PhantomData;
+use embedded_hal::blocking::delay::DelayUs;
 use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::InputPin;
+
+mod config;
+mod configured;
+mod debounce;
+mod dither;
+mod dump;
+#[cfg(feature = "alloc")]
+mod erased;
+mod expander;
+mod gate;
+#[cfg(feature = "embedded-hal-1")]
+pub mod hal1;
+mod health;
+mod owned;
+mod relay;
+mod remap;
+mod seven_seg;
+mod staging;
+mod tracker;
+pub mod variant;
+
+pub use config::{Configuration, ConfigurationDiff, RegisterDifference};
+pub use configured::ConfiguredDevice;
+pub use debounce::DebouncedInterrupts;
+pub use dither::Dither;
+pub use dump::{IoSnapshot, RegisterDump, SnapshotDiff};
+#[cfg(feature = "alloc")]
+pub use erased::ErasedPin;
+pub use expander::Expander;
+pub use gate::OutputGate;
+pub use health::HealthMonitor;
+pub use owned::OwnedPCA9554;
+pub use relay::RelayBoard;
+pub use remap::Remap;
+pub use seven_seg::SevenSeg;
+pub use staging::OutputStaging;
+pub use tracker::InputTracker;
+
+use variant::{Extended, Family, Pcal9554, Variant};
 
 bitflags::bitflags! {
     pub struct Port: u8 {
@@ -21,38 +66,478 @@ bitflags::bitflags! {
         const P07 = 0b1000_0000;
     }
 }
-pub struct PCA9554<T> {
-    address: Address,
+
+impl Port {
+    /// Reverse the pin order, i.e. swap P00 with P07, P01 with P06 and so on.  Useful for boards
+    /// wired in reverse pin order.
+    pub const fn reverse_bits(self) -> Port {
+        Port::from_bits_truncate(self.bits.reverse_bits())
+    }
+
+    /// The value following `self` in 8-bit Gray code order, which differs from `self` in exactly
+    /// one pin.  Wraps around after the last value, 0x80.
+    pub const fn next_gray(self) -> Port {
+        // Gray to binary: XOR of all higher bits.
+        let mut binary = self.bits;
+        let mut shift = 1;
+        while shift < 8 {
+            binary ^= binary >> shift;
+            shift <<= 1;
+        }
+        let next = binary.wrapping_add(1);
+        Port::from_bits_truncate(next ^ (next >> 1))
+    }
+
+    /// Return `self` if exactly one pin is set, `Error::NotSinglePin` otherwise.
+    pub fn try_single(self) -> Result<Port, Error<()>> {
+        if self.bits.count_ones() == 1 {
+            Ok(self)
+        } else {
+            Err(Error::NotSinglePin)
+        }
+    }
+
+    /// The states of P00–P03 as a number `0..=15`, P00 being the least significant bit.
+    pub const fn low_nibble(self) -> u8 {
+        self.bits & 0x0F
+    }
+
+    /// The states of P04–P07 as a number `0..=15`, P04 being the least significant bit.
+    pub const fn high_nibble(self) -> u8 {
+        self.bits >> 4
+    }
+
+    /// Combine two nibbles into pin states, `low` for P00–P03 and `high` for P04–P07.  Only the
+    /// lower four bits of each are used.
+    pub const fn from_nibbles(low: u8, high: u8) -> Port {
+        Port::from_bits_truncate((low & 0x0F) | (high << 4))
+    }
+
+    /// Write the pin states into `buf` as ASCII `'0'`/`'1'`, P07 first, e.g. `b"10110010"`.
+    pub fn to_ascii_bits(self, buf: &mut [u8; 8]) {
+        for (index, byte) in buf.iter_mut().enumerate() {
+            *byte = if self.bits & (0x80 >> index) != 0 {
+                b'1'
+            } else {
+                b'0'
+            };
+        }
+    }
+}
+
+/// Every bit pattern is a valid `Port`.
+impl From<u8> for Port {
+    fn from(bits: u8) -> Self {
+        Port::from_bits_truncate(bits)
+    }
+}
+
+/// One `bool` per pin, P00 first.
+impl From<[bool; 8]> for Port {
+    fn from(pins: [bool; 8]) -> Self {
+        let bits = pins
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (index, &set)| bits | (u8::from(set) << index));
+        Port::from_bits_truncate(bits)
+    }
+}
+
+impl From<Port> for u8 {
+    fn from(port: Port) -> Self {
+        port.bits
+    }
+}
+
+/// A value that can be written to a register: a typed `Port` or a raw `u8`.
+pub trait RegisterValue {
+    fn to_register_value(self) -> u8;
+}
+
+impl RegisterValue for Port {
+    fn to_register_value(self) -> u8 {
+        self.bits
+    }
+}
+
+impl RegisterValue for u8 {
+    fn to_register_value(self) -> u8 {
+        self
+    }
+}
+
+/// A single pin, identified by its index.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PinId {
+    P0 = 0,
+    P1 = 1,
+    P2 = 2,
+    P3 = 3,
+    P4 = 4,
+    P5 = 5,
+    P6 = 6,
+    P7 = 7,
+}
+
+impl PinId {
+    /// The pin with index `index`, or `None` if `index` is above 7.
+    pub const fn from_index(index: u8) -> Option<PinId> {
+        match index {
+            0 => Some(PinId::P0),
+            1 => Some(PinId::P1),
+            2 => Some(PinId::P2),
+            3 => Some(PinId::P3),
+            4 => Some(PinId::P4),
+            5 => Some(PinId::P5),
+            6 => Some(PinId::P6),
+            7 => Some(PinId::P7),
+            _ => None,
+        }
+    }
+
+    pub const fn index(self) -> u8 {
+        self as u8
+    }
+
+    pub const fn to_port(self) -> Port {
+        Port::from_bits_truncate(1 << self as u8)
+    }
+}
+
+impl From<PinId> for Port {
+    fn from(pin: PinId) -> Self {
+        pin.to_port()
+    }
+}
+
+/// Build a `Port` from pin indices at compile time.
+///
+/// ```
+/// use pca9554_rs::{port, Port};
+///
+/// const LEDS: Port = port!(0, 3, 7);
+/// assert_eq!(LEDS, Port::P00 | Port::P03 | Port::P07);
+/// ```
+///
+/// Indices above 7 are rejected at compile time:
+///
+/// ```compile_fail
+/// let _ = pca9554_rs::port!(0, 8);
+/// ```
+#[macro_export]
+macro_rules! port {
+    ($($index:expr),* $(,)?) => {{
+        const PORT: $crate::Port = $crate::Port::from_bits_truncate(0 $(| $crate::pin_mask($index))*);
+        PORT
+    }};
+}
+
+#[doc(hidden)]
+pub const fn pin_mask(index: u8) -> u8 {
+    assert!(index < 8, "pin index out of range");
+    1 << index
+}
+
+/// Error returned when parsing a `Port` from a string fails.
+#[cfg(feature = "alloc")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParsePortError;
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for ParsePortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown pin name")
+    }
+}
+
+/// Parse pipe-separated pin names, e.g. `"P00|P03"`.  Whitespace around names is ignored and an
+/// empty string yields `Port::empty()`.
+#[cfg(feature = "alloc")]
+impl core::str::FromStr for Port {
+    type Err = ParsePortError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Ok(Port::empty());
+        }
+        s.split('|').try_fold(Port::empty(), |port, name| {
+            let pin = match name.trim() {
+                "P00" => Port::P00,
+                "P01" => Port::P01,
+                "P02" => Port::P02,
+                "P03" => Port::P03,
+                "P04" => Port::P04,
+                "P05" => Port::P05,
+                "P06" => Port::P06,
+                "P07" => Port::P07,
+                _ => return Err(ParsePortError),
+            };
+            Ok(port | pin)
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Port {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.bits)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Port {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <u8 as serde::Deserialize>::deserialize(deserializer).map(Port::from_bits_truncate)
+    }
+}
+
+/// Serializes a `Port` as an array of eight `bool`s, P00 first, instead of a `u8`.
+#[cfg(feature = "serde")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PortBools(pub Port);
+
+#[cfg(feature = "serde")]
+impl From<Port> for PortBools {
+    fn from(port: Port) -> Self {
+        PortBools(port)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<PortBools> for Port {
+    fn from(bools: PortBools) -> Self {
+        bools.0
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for PortBools {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut pins = [false; 8];
+        for (index, pin) in pins.iter_mut().enumerate() {
+            *pin = self.0.bits & (1 << index) != 0;
+        }
+        pins.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PortBools {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pins = <[bool; 8] as serde::Deserialize>::deserialize(deserializer)?;
+        Ok(PortBools(Port::from(pins)))
+    }
+}
+
+/// Errors returned by methods that can fail for reasons other than the i2c bus.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The i2c bus returned an error.
+    Bus(E),
+    /// The provided buffer is too small to hold the result.
+    BufferTooSmall,
+    /// A pin index outside `0..=7` was given.
+    InvalidPin,
+    /// The inputs did not settle on a stable value.
+    Unstable,
+    /// A register read back a different value than was written.
+    Mismatch,
+    /// The pin is configured as an output.
+    NotAnInput,
+    /// The pin is configured as an input.
+    NotAnOutput,
+    /// The selected part does not implement the register.
+    UnsupportedByVariant,
+    /// A `Port` with exactly one pin set was expected.
+    NotSinglePin,
+    /// The operation kept failing until its retries ran out.
+    Timeout,
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(error: E) -> Self {
+        Error::Bus(error)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Bus(error) => write!(f, "i2c bus error: {:?}", error),
+            Error::BufferTooSmall => f.write_str("buffer too small"),
+            Error::InvalidPin => f.write_str("pin index out of range"),
+            Error::Unstable => f.write_str("inputs did not settle"),
+            Error::Mismatch => f.write_str("register read back a different value"),
+            Error::NotAnInput => f.write_str("pin is not configured as input"),
+            Error::NotAnOutput => f.write_str("pin is not configured as output"),
+            Error::UnsupportedByVariant => f.write_str("register not supported by this part"),
+            Error::NotSinglePin => f.write_str("expected exactly one pin"),
+            Error::Timeout => f.write_str("retries exhausted"),
+        }
+    }
+}
+
+#[cfg(feature = "core-error")]
+impl<E: fmt::Debug> core::error::Error for Error<E> {}
+
+/// Errors returned by methods that wait on an MCU input pin as well as using the i2c bus.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WaitError<E, P> {
+    /// The i2c bus returned an error.
+    Bus(E),
+    /// Reading the MCU pin returned an error.
+    Pin(P),
+}
+
+impl<E: fmt::Debug, P: fmt::Debug> fmt::Display for WaitError<E, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitError::Bus(error) => write!(f, "i2c bus error: {:?}", error),
+            WaitError::Pin(error) => write!(f, "input pin error: {:?}", error),
+        }
+    }
+}
+
+#[cfg(feature = "core-error")]
+impl<E: fmt::Debug, P: fmt::Debug> core::error::Error for WaitError<E, P> {}
+
+/// Handle for one device.  The bus is owned by the caller and passed to every method.
+///
+/// The handle is `Copy` whatever `T` and `V` are.  All copies refer to the same physical device,
+/// so changes made through one of them, e.g. to the outputs, are seen by the others.  The active
+/// level is stored in the handle, so `set_active_level()` only affects the copy it is called on.
+pub struct PCA9554<T, V = Pcal9554> {
+    address: u8,
     i2c: PhantomData<T>,
+    variant: PhantomData<V>,
+    active_level: ActiveLevel,
+}
+
+// Not derived, as that would require `T: Clone` and `V: Clone`.
+impl<T, V> Clone for PCA9554<T, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, V> Copy for PCA9554<T, V> {}
+
+impl<T> PCA9554<T> {
+    pub fn new(_i2c: &T, address: Address) -> Self {
+        Self {
+            address: address as u8,
+            i2c: PhantomData,
+            variant: PhantomData,
+            active_level: ActiveLevel::High,
+        }
+    }
+
+    /// Create a handle for a device at an arbitrary 7-bit address.
+    ///
+    /// This skips the validation provided by `Address` and is meant for boards where the address
+    /// seen on the bus differs from the strapped one, e.g. behind an address translator.  Only the
+    /// lower 7 bits of `address` are used.
+    pub fn new_raw_address(_i2c: &T, address: u8) -> Self {
+        Self {
+            address: address & 0x7F,
+            i2c: PhantomData,
+            variant: PhantomData,
+            active_level: ActiveLevel::High,
+        }
+    }
 }
 
-impl<T, E> PCA9554<T>
+impl<T, V> PCA9554<T, V>
 where
-    T: WriteRead<Error = E> + Write<Error = E>,
+    V: Variant,
 {
-    pub fn new(_i2c: &T, address: Address) -> Self {
+    /// Create a handle for a specific part, e.g.
+    /// `PCA9554::with_variant(&i2c, Address::ADDR_0x20, variant::Pca9554)`.
+    pub fn with_variant(_i2c: &T, address: Address, _variant: V) -> Self {
         Self {
-            address,
+            address: address as u8,
             i2c: PhantomData,
+            variant: PhantomData,
+            active_level: ActiveLevel::High,
         }
     }
 
-    pub fn address(&self) -> Address {
+    /// The address of the device, or `None` if it was created with `new_raw_address()` and the
+    /// address is outside the PCA9554 address range.
+    pub fn address(&self) -> Option<Address> {
+        Address::try_from(self.address).ok()
+    }
+
+    /// The raw 7-bit address of the device, including addresses set through
+    /// `new_raw_address()`.
+    pub fn address_u8(&self) -> u8 {
         self.address
     }
 
+    /// The first byte on the bus when writing to the device: the address followed by the R/W bit
+    /// cleared.
+    pub fn write_frame_byte(&self) -> u8 {
+        self.address << 1
+    }
+
+    /// The first byte on the bus when reading from the device: the address followed by the R/W
+    /// bit set.
+    pub fn read_frame_byte(&self) -> u8 {
+        (self.address << 1) | 1
+    }
+
+    /// Select which physical level `set_pin_on()` and `set_pin_off()` treat as "on".  Defaults to
+    /// `ActiveLevel::High`.
+    pub fn set_active_level(&mut self, level: ActiveLevel) {
+        self.active_level = level;
+    }
+
+    pub fn active_level(&self) -> ActiveLevel {
+        self.active_level
+    }
+
+    fn check_supported<E>(reg: Register) -> Result<(), Error<E>> {
+        if reg.is_extended() && !V::EXTENDED_REGISTERS {
+            return Err(Error::UnsupportedByVariant);
+        }
+        Ok(())
+    }
+}
+
+impl<T, E, V> PCA9554<T, V>
+where
+    T: WriteRead<Error = E>,
+    V: Variant,
+{
     /// Read a register.
     fn read(&self, i2c: &mut T, reg: Register) -> Result<Port, E> {
         let mut buffer = [0u8; 1];
-        i2c.write_read(self.address as u8, &[reg as u8], &mut buffer)
-            .map(|_| unsafe { Port::from_bits_unchecked(u8::from_le_bytes(buffer)) })
+        self.read_into(i2c, reg, &mut buffer)
     }
 
-    /// Write a register.
-    fn write(&self, i2c: &mut T, reg: Register, port: Port) -> Result<(), E> {
-        let bytes = port.bits.to_le_bytes();
-        let buffer = [reg as u8, bytes[0]];
-        i2c.write(self.address as u8, &buffer)
+    /// Read a register using the caller's buffer.
+    ///
+    /// The `WriteRead` trait of embedded-hal 0.2 does not report how many bytes were received, so
+    /// a short read cannot be detected here.  The HAL has to return an error when it could not fill
+    /// `buffer`; one that returns `Ok` anyway leaves the previous contents of `buffer` in place.
+    fn read_into(&self, i2c: &mut T, reg: Register, buffer: &mut [u8; 1]) -> Result<Port, E> {
+        i2c.write_read(self.address, &[reg as u8], buffer)?;
+        let value = unsafe { Port::from_bits_unchecked(u8::from_le_bytes(*buffer)) };
+        #[cfg(feature = "log")]
+        log::trace!(
+            "{:#04x}: read {:?} = {:#04x}",
+            self.address,
+            reg,
+            value.bits
+        );
+        Ok(value)
+    }
+
+    /// Read any register.  Returns `Error::UnsupportedByVariant` for extended registers on parts
+    /// without them.
+    pub fn read_register(&self, i2c: &mut T, reg: Register) -> Result<Port, Error<E>> {
+        Self::check_supported(reg)?;
+        Ok(self.read(i2c, reg)?)
     }
 
     /// The Input Port register reflect the incoming logic levels of the pins, regardless of
@@ -61,6 +546,57 @@ where
         self.read(i2c, Register::INPUT_PORT)
     }
 
+    /// Same as `read_inputs()`, but reads into a caller-provided buffer instead of one on the
+    /// stack.  The raw register byte is left in `buf`.
+    ///
+    /// The byte is not cleared first: if the HAL reports success without receiving it, `buf` keeps
+    /// its previous value.
+    pub fn read_inputs_into(&self, i2c: &mut T, buf: &mut [u8; 1]) -> Result<Port, E> {
+        self.read_into(i2c, Register::INPUT_PORT, buf)
+    }
+
+    /// Sample the Input Port register up to `reads` times, tracking each pin separately.  A pin
+    /// is stable once its level has been the same for the last `required_matches` samples.
+    ///
+    /// Sampling stops as soon as every pin is stable and the last sample is returned.  Otherwise,
+    /// after `reads` samples, the levels of the stable pins are returned and pins that are still
+    /// changing read as 0, so one bouncing pin does not hold up the others.  Returns
+    /// `Error::Unstable` if no pin is stable at that point.
+    ///
+    /// A `required_matches` of 0 is treated as 1.
+    pub fn read_inputs_stable(
+        &self,
+        i2c: &mut T,
+        reads: u8,
+        required_matches: u8,
+    ) -> Result<Port, Error<E>> {
+        let required_matches = required_matches.max(1);
+        let mut last = Port::empty();
+        let mut runs = [0u8; 8];
+        let mut stable = Port::empty();
+        for _ in 0..reads {
+            let inputs = self.read(i2c, Register::INPUT_PORT)?;
+            let changed = inputs ^ last;
+            for (index, run) in runs.iter_mut().enumerate() {
+                let pin = Port::from_bits_truncate(1 << index);
+                *run = if *run == 0 || changed.contains(pin) {
+                    1
+                } else {
+                    run.saturating_add(1)
+                };
+                stable.set(pin, *run >= required_matches);
+            }
+            last = inputs;
+            if stable.is_all() {
+                return Ok(last);
+            }
+        }
+        if stable.is_empty() {
+            return Err(Error::Unstable);
+        }
+        Ok(last & stable)
+    }
+
     /// The Output Port register show the outgoing logic levels of the pins defined as outputs
     /// by the Configuration Register.  These values reflect the state of the flip-flop controlling
     /// the output section, not the actual pin value.
@@ -68,6 +604,310 @@ where
         self.read(i2c, Register::OUTPUT_PORT)
     }
 
+    /// Count the pins that are actively pulling low.  See [`PCA9554::sinking_outputs`].
+    pub fn driven_low_count(&self, i2c: &mut T) -> Result<u32, E> {
+        Ok(self.sinking_outputs(i2c)?.bits.count_ones())
+    }
+
+    /// The pins that are actively pulling low, i.e. pins configured as outputs (`CONFIG_PORT` bit
+    /// cleared) whose Output Port flip-flop is 0.
+    ///
+    /// The output mode in `OUTPUT_PORT_CONFIG` is not consulted: push-pull and open-drain outputs
+    /// both sink current when driven low and only differ in how they drive a 1.
+    pub fn sinking_outputs(&self, i2c: &mut T) -> Result<Port, E> {
+        let outputs = self.read(i2c, Register::OUTPUT_PORT)?;
+        let config = self.read(i2c, Register::CONFIG_PORT)?;
+        Ok(!config & !outputs)
+    }
+
+    /// Read the direction of the I/O pins.  Ports set to 1 are configured as input pins with
+    /// high-impedance output drivers.  Ports set to 0 are set as output pins.
+    pub fn read_config(&self, i2c: &mut T) -> Result<Port, E> {
+        self.read(i2c, Register::CONFIG_PORT)
+    }
+
+    /// The Polarity Inversion register allow polarity inversion of pins defined as inputs by the
+    /// Configuration register. If a bit in this register is set the corresponding pin's polarity
+    /// is inverted. If a bit in this register is cleared, the corresponding pin's original polarity
+    /// is retained.
+    pub fn is_inverted(&self, i2c: &mut T) -> Result<Port, E> {
+        self.read(i2c, Register::POLARITY_INVERSION)
+    }
+
+    /// Query the Polarity Inversion register for a single pin.  If `pin` holds several pins, `true`
+    /// is returned only if all of them are inverted.
+    pub fn is_pin_inverted(&self, i2c: &mut T, pin: Port) -> Result<bool, E> {
+        self.is_inverted(i2c).map(|inverted| inverted.contains(pin))
+    }
+
+    /// Read the logical level of `pin`, i.e. its electrical level inverted if the pin is set in the
+    /// Polarity Inversion register.  If `pin` holds several pins, `true` is returned only if all of
+    /// them are logically high.
+    ///
+    /// The device applies the inversion itself before latching the Input Port register, so this
+    /// reads only that register.  Inverting again in software would report the electrical level
+    /// for inverted pins.
+    pub fn read_pin_logical(&self, i2c: &mut T, pin: Port) -> Result<bool, E> {
+        self.read_inputs(i2c).map(|inputs| inputs.contains(pin))
+    }
+
+    /// Check that the Configuration register still holds `expected`.
+    pub fn config_matches(&self, i2c: &mut T, expected: Port) -> Result<bool, E> {
+        self.read_config(i2c).map(|config| config == expected)
+    }
+
+    /// Read the level of an input pin, after checking in the Configuration register that it
+    /// actually is an input.  Returns `Error::NotAnInput` for output pins, whose Input Port bit
+    /// only reflects the level being driven.
+    pub fn read_input_checked(&self, i2c: &mut T, pin: Port) -> Result<bool, Error<E>> {
+        if !self.read_config(i2c)?.contains(pin) {
+            return Err(Error::NotAnInput);
+        }
+        Ok(self.read_inputs(i2c)?.contains(pin))
+    }
+
+    /// Wait for the active-low INT line, connected to the MCU pin `int_pin`, to assert and then
+    /// read the Input Port register.  The read clears the interrupt; the inputs are returned.
+    ///
+    /// This busy-waits on `int_pin` without any timeout.  An error reading `int_pin` stops the
+    /// wait and is returned as `WaitError::Pin`, without touching the bus.
+    pub fn wait_and_service<P>(
+        &self,
+        i2c: &mut T,
+        int_pin: &P,
+    ) -> Result<Port, WaitError<E, P::Error>>
+    where
+        P: InputPin,
+    {
+        while !int_pin.is_low().map_err(WaitError::Pin)? {}
+        self.read_inputs(i2c).map_err(WaitError::Bus)
+    }
+
+    /// Read the Input Port, Output Port, Polarity Inversion and Configuration registers, in that
+    /// order.
+    ///
+    /// The device does not auto-increment the command byte, so this takes one transaction per
+    /// register.  The snapshot is not atomic: an input may change between the reads.
+    pub fn core_snapshot(&self, i2c: &mut T) -> Result<[u8; 4], E> {
+        Ok([
+            self.read(i2c, Register::INPUT_PORT)?.bits,
+            self.read(i2c, Register::OUTPUT_PORT)?.bits,
+            self.read(i2c, Register::POLARITY_INVERSION)?.bits,
+            self.read(i2c, Register::CONFIG_PORT)?.bits,
+        ])
+    }
+
+    /// Read the Input Port register once per element of `out`, back to back, storing each
+    /// sample.  The sample rate is limited only by the bus.
+    pub fn sample_inputs(&self, i2c: &mut T, out: &mut [Port]) -> Result<(), E> {
+        for sample in out.iter_mut() {
+            *sample = self.read_inputs(i2c)?;
+        }
+        Ok(())
+    }
+
+    /// Sample the Input Port register `reads` times and return the pins whose level never
+    /// changed.  "Stuck" only means unchanging here; whether that indicates a fault, e.g. a
+    /// shorted pin, is up to the caller.  With `reads` below 2 every pin is reported.
+    pub fn detect_stuck(&self, i2c: &mut T, reads: u8) -> Result<Port, E> {
+        let mut stuck = Port::all();
+        let mut first = None;
+        for _ in 0..reads {
+            let inputs = self.read_inputs(i2c)?;
+            let first = *first.get_or_insert(inputs);
+            stuck.remove(inputs ^ first);
+        }
+        Ok(stuck)
+    }
+
+    /// Read the inputs, update `tracker` and return one `(pin_index, rising, timestamp)` entry per
+    /// changed pin, lowest index first.  `clock` is called once per read and its value is used as
+    /// the timestamp of every change seen by that read.
+    pub fn timed_update<C: FnMut() -> u32>(
+        &self,
+        i2c: &mut T,
+        tracker: &mut InputTracker,
+        clock: &mut C,
+    ) -> Result<heapless::Vec<(u8, bool, u32), 8>, E> {
+        let inputs = self.read_inputs(i2c)?;
+        let timestamp = clock();
+        let (rising, falling) = tracker.update(inputs);
+        let mut events = heapless::Vec::new();
+        for index in 0..8 {
+            let pin = Port::from_bits_truncate(1 << index);
+            if rising.contains(pin) || falling.contains(pin) {
+                // At most eight pins can change, so this never overflows.
+                let _ = events.push((index, rising.contains(pin), timestamp));
+            }
+        }
+        Ok(events)
+    }
+
+    /// Whether every input in `chord` reads high at the same time, e.g. all buttons of a key
+    /// combination being held.  Levels are logical, see `read_pin_logical()`.
+    pub fn chord_pressed(&self, i2c: &mut T, chord: Port) -> Result<bool, E> {
+        self.read_pin_logical(i2c, chord)
+    }
+
+    /// Return the pins whose direction would change if `desired` was written to the
+    /// Configuration register.  An empty result means the write can be skipped.
+    pub fn direction_delta(&self, i2c: &mut T, desired: Port) -> Result<Port, E> {
+        self.read_config(i2c).map(|config| config ^ desired)
+    }
+
+    /// Read the Input Port, Output Port, Polarity Inversion and Configuration registers.
+    ///
+    /// This costs four transactions, see [`PCA9554::core_snapshot`].
+    pub fn io_snapshot(&self, i2c: &mut T) -> Result<IoSnapshot, E> {
+        Ok(IoSnapshot {
+            inputs: self.read(i2c, Register::INPUT_PORT)?,
+            outputs: self.read(i2c, Register::OUTPUT_PORT)?,
+            polarity: self.read(i2c, Register::POLARITY_INVERSION)?,
+            config: self.read(i2c, Register::CONFIG_PORT)?,
+        })
+    }
+
+    /// Read the inputs, keeping only the pins configured as inputs.  Pins configured as outputs
+    /// read as 0.
+    pub fn read_configured_inputs(&self, i2c: &mut T) -> Result<Port, E> {
+        let config = self.read_config(i2c)?;
+        Ok(self.read_inputs(i2c)? & config)
+    }
+
+    /// Return the index of the lowest numbered pin whose Output Port bit is 0, or `None` if all
+    /// outputs are high.
+    pub fn first_low_output(&self, i2c: &mut T) -> Result<Option<u8>, E> {
+        let outputs = self.read_outputs(i2c)?;
+        if outputs.is_all() {
+            Ok(None)
+        } else {
+            Ok(Some((!outputs).bits.trailing_zeros() as u8))
+        }
+    }
+
+    /// Check whether the device answers, trying up to `attempts` times with `delay_us`
+    /// microseconds between tries.  Useful for parts on a switched supply that take a moment to
+    /// come up.
+    ///
+    /// Each try reads the Configuration register, which unlike the Input Port register does not
+    /// clear a pending interrupt.  embedded-hal 0.2 does not tell a NACK apart from other bus
+    /// errors, so every bus error counts as the device not answering and `Ok(false)` is returned
+    /// once all attempts failed.
+    pub fn probe_with_retry<D: DelayUs<u32>>(
+        &self,
+        i2c: &mut T,
+        delay: &mut D,
+        attempts: u8,
+        delay_us: u32,
+    ) -> Result<bool, E> {
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                delay.delay_us(delay_us);
+            }
+            if self.read_config(i2c).is_ok() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Read the inputs selected by `mask` as a number, shifted right by `shift`, e.g. the value of
+    /// a rotary switch on P00–P03 with `mask` 0x0F and `shift` 0.
+    pub fn read_field(&self, i2c: &mut T, mask: Port, shift: u8) -> Result<u8, E> {
+        let inputs = self.read_inputs(i2c)?;
+        Ok((inputs & mask)
+            .bits
+            .checked_shr(u32::from(shift))
+            .unwrap_or(0))
+    }
+
+    /// Read the inputs, retrying up to `retries` times with `delay_us` microseconds in between
+    /// when the bus reports an error.  Returns `Error::Timeout` if every attempt failed.
+    pub fn read_inputs_timeout<D: DelayUs<u32>>(
+        &self,
+        i2c: &mut T,
+        delay: &mut D,
+        retries: u8,
+        delay_us: u32,
+    ) -> Result<Port, Error<E>> {
+        for attempt in 0..=retries {
+            if attempt > 0 {
+                delay.delay_us(delay_us);
+            }
+            if let Ok(inputs) = self.read_inputs(i2c) {
+                return Ok(inputs);
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    /// Whether every Output Port bit is 1.
+    pub fn outputs_all_high(&self, i2c: &mut T) -> Result<bool, E> {
+        self.read_outputs(i2c).map(|outputs| outputs.is_all())
+    }
+
+    /// Whether every Output Port bit is 0.
+    pub fn outputs_all_low(&self, i2c: &mut T) -> Result<bool, E> {
+        self.read_outputs(i2c).map(|outputs| outputs.is_empty())
+    }
+
+    /// Read the Output Port register and panic with a readable message unless it holds
+    /// `expected`.  Meant for host-side tests of code driving the device.
+    #[cfg(feature = "test-helpers")]
+    pub fn assert_outputs(&self, i2c: &mut T, expected: Port) -> Result<(), E> {
+        let outputs = self.read_outputs(i2c)?;
+        assert!(
+            outputs == expected,
+            "outputs of device {:#04x}: expected {:#010b} ({:?}), read {:#010b} ({:?})",
+            self.address,
+            expected.bits,
+            expected,
+            outputs.bits,
+            outputs
+        );
+        Ok(())
+    }
+
+    /// Read the inputs and return `(rising, falling)`, the pins that are high but low in
+    /// `reference` and the pins that are low but high in `reference`.  This is the stateless
+    /// counterpart of `InputTracker`.
+    pub fn edges_since(&self, i2c: &mut T, reference: Port) -> Result<(Port, Port), E> {
+        let inputs = self.read_inputs(i2c)?;
+        Ok(InputTracker::new(reference).update(inputs))
+    }
+}
+
+impl<T, E, V> PCA9554<T, V>
+where
+    T: Write<Error = E>,
+    V: Variant,
+{
+    /// Write a register.
+    fn write(&self, i2c: &mut T, reg: Register, port: Port) -> Result<(), E> {
+        let bytes = port.bits.to_le_bytes();
+        let buffer = [reg as u8, bytes[0]];
+        i2c.write(self.address, &buffer)?;
+        #[cfg(feature = "log")]
+        log::trace!(
+            "{:#04x}: wrote {:?} = {:#04x}",
+            self.address,
+            reg,
+            port.bits
+        );
+        Ok(())
+    }
+
+    /// Write any register.  Returns `Error::UnsupportedByVariant` for extended registers on parts
+    /// without them.
+    pub fn write_register<R>(&self, i2c: &mut T, reg: Register, value: R) -> Result<(), Error<E>>
+    where
+        R: RegisterValue,
+    {
+        Self::check_supported(reg)?;
+        let value = Port::from(value.to_register_value());
+        Ok(self.write(i2c, reg, value)?)
+    }
+
     /// Set the output state for all pins configured as output pins in the Configuration Register.
     /// Has no effect for pins configured as input pins.
     ///
@@ -89,12 +929,6 @@ where
         self.write(i2c, Register::CONFIG_PORT, config)
     }
 
-    /// Read the direction of the I/O pins.  Ports set to 1 are configured as input pins with
-    /// high-impedance output drivers.  Ports set to 0 are set as output pins.
-    pub fn read_config(&self, i2c: &mut T) -> Result<Port, E> {
-        self.read(i2c, Register::CONFIG_PORT)
-    }
-
     /// The Polarity Inversion register allow polarity inversion of pins defined as inputs by the
     /// Configuration register. If a bit in this register is set the corresponding pin's polarity
     /// is inverted. If a bit in this register is cleared, the corresponding pin's original polarity
@@ -103,20 +937,618 @@ where
         self.write(i2c, Register::POLARITY_INVERSION, invert)
     }
 
-    /// The Polarity Inversion register allow polarity inversion of pins defined as inputs by the
-    /// Configuration register. If a bit in this register is set the corresponding pin's polarity
-    /// is inverted. If a bit in this register is cleared, the corresponding pin's original polarity
-    /// is retained.
-    pub fn is_inverted(&self, i2c: &mut T) -> Result<Port, E> {
-        self.read(i2c, Register::POLARITY_INVERSION)
+    /// Restore a snapshot taken by `core_snapshot()`, writing the Output Port, Polarity Inversion
+    /// and Configuration registers in that order, one transaction each.  The read-only Input Port
+    /// byte is skipped.
+    ///
+    /// The outputs are written before the directions, so pins switching to outputs start at the
+    /// saved level.
+    pub fn core_restore(&self, i2c: &mut T, snapshot: &[u8; 4]) -> Result<(), E> {
+        let [_, outputs, polarity, config] = snapshot.map(Port::from_bits_truncate);
+        self.write(i2c, Register::OUTPUT_PORT, outputs)?;
+        self.write(i2c, Register::POLARITY_INVERSION, polarity)?;
+        self.write(i2c, Register::CONFIG_PORT, config)
     }
-}
 
-/// Valid addresses for the PCA9554
-#[allow(non_camel_case_types)]
-#[repr(u8)]
-#[derive(Copy, Clone)]
-pub enum Address {
+    /// Same as `set_inverted()`, taking one `bool` per pin, P00 first.
+    pub fn set_inverted_array(&self, i2c: &mut T, arr: [bool; 8]) -> Result<(), E> {
+        self.set_inverted(i2c, Port::from(arr))
+    }
+
+    /// Drive the lowest `level` outputs high and the others low, e.g. level 3 writes
+    /// `0b0000_0111`.  Levels above 8 light all outputs.
+    pub fn set_bargraph(&self, i2c: &mut T, level: u8) -> Result<(), E> {
+        let bits = match level {
+            0..=7 => (1u8 << level) - 1,
+            _ => 0xFF,
+        };
+        self.write_outputs(i2c, Port::from(bits))
+    }
+
+    /// Write `safe` to the Output Port register while the pins are still inputs, e.g. right after
+    /// reset, so that every pin later switched to an output immediately presents its safe level.
+    /// The Configuration register is not touched.
+    pub fn prime_safe_outputs(&self, i2c: &mut T, safe: Port) -> Result<(), E> {
+        self.write_outputs(i2c, safe)
+    }
+}
+
+impl<T, E, V> PCA9554<T, V>
+where
+    T: WriteRead<Error = E> + Write<Error = E>,
+    V: Variant,
+{
+    /// Read-modify-write a register and return the value written.
+    fn modify<F>(&self, i2c: &mut T, reg: Register, f: F) -> Result<Port, E>
+    where
+        F: FnOnce(Port) -> Port,
+    {
+        let value = f(self.read(i2c, reg)?);
+        self.write(i2c, reg, value)?;
+        Ok(value)
+    }
+
+    /// Read the Output Port register, transform it with `f` and write the result back in a single
+    /// write.  Returns the value written.
+    pub fn modify_outputs<F>(&self, i2c: &mut T, f: F) -> Result<Port, E>
+    where
+        F: FnOnce(Port) -> Port,
+    {
+        self.modify(i2c, Register::OUTPUT_PORT, f)
+    }
+
+    /// Set the outputs to `outputs | mask` and return the new Output Port value.
+    pub fn or_outputs(&self, i2c: &mut T, mask: Port) -> Result<Port, E> {
+        self.modify_outputs(i2c, |outputs| outputs | mask)
+    }
+
+    /// Set the outputs to `outputs & mask` and return the new Output Port value.
+    pub fn and_outputs(&self, i2c: &mut T, mask: Port) -> Result<Port, E> {
+        self.modify_outputs(i2c, |outputs| outputs & mask)
+    }
+
+    /// Set the outputs to `outputs ^ mask` and return the new Output Port value.
+    pub fn xor_outputs(&self, i2c: &mut T, mask: Port) -> Result<Port, E> {
+        self.modify_outputs(i2c, |outputs| outputs ^ mask)
+    }
+
+    /// Invert the state of every output and return the new Output Port value.
+    pub fn invert_all_outputs(&self, i2c: &mut T) -> Result<Port, E> {
+        self.modify(i2c, Register::OUTPUT_PORT, |outputs| !outputs)
+    }
+
+    /// Drive a single output pin high or low.  The other outputs are left unchanged.
+    pub fn set_pin(&self, i2c: &mut T, pin: PinId, high: bool) -> Result<(), E> {
+        self.modify_outputs(i2c, |mut outputs| {
+            outputs.set(pin.to_port(), high);
+            outputs
+        })?;
+        Ok(())
+    }
+
+    /// Drive a single output pin, addressed by its index `0..=7`, high or low.  The other outputs
+    /// are left unchanged.
+    pub fn set_pin_index(&self, i2c: &mut T, index: u8, high: bool) -> Result<(), Error<E>> {
+        let pin = PinId::from_index(index).ok_or(Error::InvalidPin)?;
+        Ok(self.set_pin(i2c, pin, high)?)
+    }
+
+    /// Drive `pin` to its active level for `width_us` microseconds, then back to its inactive
+    /// level.  Other outputs are left unchanged.
+    pub fn pulse_pin_timed<D: DelayUs<u32>>(
+        &self,
+        i2c: &mut T,
+        delay: &mut D,
+        pin: Port,
+        active_high: bool,
+        width_us: u32,
+    ) -> Result<(), E> {
+        let outputs = self.read_outputs(i2c)?;
+        let (high, low) = (outputs | pin, outputs - pin);
+        let (active, inactive) = if active_high {
+            (high, low)
+        } else {
+            (low, high)
+        };
+        self.write_outputs(i2c, active)?;
+        delay.delay_us(width_us);
+        self.write_outputs(i2c, inactive)
+    }
+
+    /// Flip `state` and drive `pin` to the new level.  Call this periodically to produce a
+    /// heartbeat signal.  `state` is only updated if the write succeeds.
+    pub fn heartbeat(&self, i2c: &mut T, pin: Port, state: &mut bool) -> Result<(), E> {
+        let high = !*state;
+        self.modify_outputs(i2c, |mut outputs| {
+            outputs.set(pin, high);
+            outputs
+        })?;
+        *state = high;
+        Ok(())
+    }
+
+    /// Write `value` to the pins currently configured as outputs only, leaving the Output Port
+    /// bits of input pins untouched.  Returns the value written.
+    pub fn write_outputs_to_configured(&self, i2c: &mut T, value: Port) -> Result<Port, E> {
+        let outputs_mask = !self.read_config(i2c)?;
+        self.modify_outputs(i2c, |outputs| {
+            (outputs - outputs_mask) | (value & outputs_mask)
+        })
+    }
+
+    /// Switch the given output pins on, i.e. drive them to the configured active level.
+    pub fn set_pin_on(&self, i2c: &mut T, pins: Port) -> Result<(), E> {
+        self.set_pins_active(i2c, pins, true)
+    }
+
+    /// Switch the given output pins off, i.e. drive them to the inverse of the configured active
+    /// level.
+    pub fn set_pin_off(&self, i2c: &mut T, pins: Port) -> Result<(), E> {
+        self.set_pins_active(i2c, pins, false)
+    }
+
+    fn set_pins_active(&self, i2c: &mut T, pins: Port, on: bool) -> Result<(), E> {
+        let high = on == (self.active_level == ActiveLevel::High);
+        self.modify_outputs(i2c, |mut outputs| {
+            outputs.set(pins, high);
+            outputs
+        })?;
+        Ok(())
+    }
+
+    /// Start staging output changes to be written in one go, e.g.
+    /// `device.stage().set(Port::P00).toggle(Port::P01).commit(&mut i2c)`.
+    pub fn stage(&self) -> OutputStaging<'_, T, V> {
+        OutputStaging::new(self)
+    }
+
+    /// Start driving `pin` at the given level.  The Output Port register is updated before the
+    /// pin is switched to an output, so an input pin never briefly drives a stale level.
+    pub fn drive_pin(&self, i2c: &mut T, pin: Port, high: bool) -> Result<(), E> {
+        self.modify_outputs(i2c, |mut outputs| {
+            outputs.set(pin, high);
+            outputs
+        })?;
+        self.modify(i2c, Register::CONFIG_PORT, |config| config - pin)?;
+        Ok(())
+    }
+
+    /// Same as `modify_outputs()`, but runs the read-modify-write inside
+    /// `critical_section::with()` so that it cannot interleave with another guarded
+    /// read-modify-write and lose its update.
+    ///
+    /// This only protects against other tasks and interrupt handlers on the same core, and only
+    /// if they also use the guarded methods.  It does not serialize access from another bus
+    /// master.
+    ///
+    /// The critical section is held across both i2c transfers, which on most implementations
+    /// means interrupts stay masked for the whole read-modify-write.  The `i2c` implementation
+    /// must therefore complete transfers by polling: a HAL that waits for an interrupt or DMA
+    /// completion will deadlock.  With a polling HAL the interrupt latency grows by the duration
+    /// of the two transfers.
+    #[cfg(feature = "critical-section")]
+    pub fn modify_outputs_atomic<F>(&self, i2c: &mut T, f: F) -> Result<Port, E>
+    where
+        F: FnOnce(Port) -> Port,
+    {
+        critical_section::with(|_| self.modify_outputs(i2c, f))
+    }
+
+    /// Add one to the Output Port register, read as a number, wrapping from 0xFF to 0x00.
+    /// Returns the value written.
+    pub fn increment_outputs(&self, i2c: &mut T) -> Result<Port, E> {
+        self.modify_outputs(i2c, |outputs| Port::from(outputs.bits.wrapping_add(1)))
+    }
+
+    /// Switch every output on, i.e. to the configured active level, for `width_us` microseconds
+    /// and then restore the previous Output Port value.
+    ///
+    /// Only pins configured as outputs are affected, as the Output Port register has no effect on
+    /// input pins.
+    pub fn lamp_test<D: DelayUs<u32>>(
+        &self,
+        i2c: &mut T,
+        delay: &mut D,
+        width_us: u32,
+    ) -> Result<(), E> {
+        let saved = self.read_outputs(i2c)?;
+        let on = match self.active_level {
+            ActiveLevel::High => Port::all(),
+            ActiveLevel::Low => Port::empty(),
+        };
+        self.write_outputs(i2c, on)?;
+        delay.delay_us(width_us);
+        self.write_outputs(i2c, saved)
+    }
+
+    /// Write `value`, shifted left by `shift`, to the outputs selected by `mask`, leaving the
+    /// other outputs unchanged.  Bits of `value` falling outside `mask` are dropped.  Returns the
+    /// value written.
+    pub fn write_field(&self, i2c: &mut T, mask: Port, shift: u8, value: u8) -> Result<Port, E> {
+        let field = Port::from(value.checked_shl(u32::from(shift)).unwrap_or(0)) & mask;
+        self.modify_outputs(i2c, |outputs| (outputs - mask) | field)
+    }
+
+    /// Write `desired` to the Output Port register unless it already holds that value.  Returns
+    /// whether a write was made.
+    pub fn ensure_outputs(&self, i2c: &mut T, desired: Port) -> Result<bool, E> {
+        if self.read_outputs(i2c)? == desired {
+            return Ok(false);
+        }
+        self.write_outputs(i2c, desired)?;
+        Ok(true)
+    }
+
+    /// Advance the Output Port register to the next value in Gray code order, see
+    /// `Port::next_gray()`.  Returns the value written.
+    pub fn step_gray(&self, i2c: &mut T) -> Result<Port, E> {
+        self.modify_outputs(i2c, Port::next_gray)
+    }
+
+    /// Drive `pin` high, read the Input Port register `dummy_reads` times to let time pass, then
+    /// drive `pin` low.  Other outputs are left unchanged.
+    ///
+    /// This is a crude pulse for callers without a delay source: its width depends on the bus
+    /// clock, clock stretching, the HAL and anything else competing for the bus, and can only be
+    /// estimated as roughly `dummy_reads` read transactions.  The dummy reads also clear a pending
+    /// interrupt.
+    ///
+    /// If a dummy read fails, the remaining reads are skipped and `pin` is still driven low before
+    /// the read error is returned.
+    pub fn hold_pin_high(&self, i2c: &mut T, pin: Port, dummy_reads: u32) -> Result<(), E> {
+        let outputs = self.read_outputs(i2c)?;
+        self.write_outputs(i2c, outputs | pin)?;
+        let held = (0..dummy_reads).try_for_each(|_| self.read_inputs(i2c).map(drop));
+        let restored = self.write_outputs(i2c, outputs - pin);
+        held.and(restored)
+    }
+
+    /// Shift the Output Port register by one pin, towards P07 if `left` is true and towards P00
+    /// otherwise.  The vacated pin is set to `fill` and the pin shifted off the end is discarded.
+    /// Returns the value written.
+    pub fn shift_outputs(&self, i2c: &mut T, left: bool, fill: bool) -> Result<Port, E> {
+        self.modify_outputs(i2c, |outputs| {
+            let (shifted, vacated) = if left {
+                (outputs.bits << 1, Port::P00)
+            } else {
+                (outputs.bits >> 1, Port::P07)
+            };
+            let mut shifted = Port::from(shifted);
+            shifted.set(vacated, fill);
+            shifted
+        })
+    }
+
+    /// Read the Input Port register and write the value to the Output Port register.  Returns
+    /// the captured inputs.
+    ///
+    /// The inputs are read with polarity inversion applied, see [`PCA9554::set_inverted`].
+    pub fn latch_inputs_to_outputs(&self, i2c: &mut T) -> Result<Port, E> {
+        let inputs = self.read_inputs(i2c)?;
+        self.write_outputs(i2c, inputs)?;
+        Ok(inputs)
+    }
+}
+
+impl<T, E, V> PCA9554<T, V>
+where
+    T: WriteRead<Error = E>,
+    V: Extended,
+{
+    /// The Interrupt Mask register enables interrupts per pin.  Pins set to 1 are masked and do
+    /// not generate an interrupt, pins set to 0 do.
+    pub fn read_interrupt_mask(&self, i2c: &mut T) -> Result<Port, E> {
+        self.read(i2c, Register::INTERRUPT_MASK)
+    }
+
+    /// The Interrupt Status register identifies the pins that caused the interrupt.  It is cleared
+    /// by reading the Input Port register.
+    pub fn read_interrupt_status(&self, i2c: &mut T) -> Result<Port, E> {
+        self.read(i2c, Register::INTERRUPT_STATUS)
+    }
+
+    /// Read the Interrupt Mask and Interrupt Status registers and return `(mask, status)`.
+    ///
+    /// The status is read last so that an input change occurring between the two reads is still
+    /// reported.
+    pub fn interrupt_state(&self, i2c: &mut T) -> Result<(Port, Port), E> {
+        let mask = self.read_interrupt_mask(i2c)?;
+        let status = self.read_interrupt_status(i2c)?;
+        Ok((mask, status))
+    }
+
+    /// Return the index of the lowest numbered pin flagged in the Interrupt Status register, or
+    /// `None` if no interrupt is pending.  Lower pin numbers have higher priority.
+    pub fn highest_priority_interrupt(&self, i2c: &mut T) -> Result<Option<u8>, E> {
+        let status = self.read_interrupt_status(i2c)?;
+        if status.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(status.bits.trailing_zeros() as u8))
+        }
+    }
+
+    /// Read every register of the device.
+    ///
+    /// Note that reading the Input Port register clears a pending interrupt.
+    pub fn dump(&self, i2c: &mut T) -> Result<RegisterDump, E> {
+        Ok(RegisterDump {
+            inputs: self.read(i2c, Register::INPUT_PORT)?,
+            outputs: self.read(i2c, Register::OUTPUT_PORT)?,
+            polarity: self.read(i2c, Register::POLARITY_INVERSION)?,
+            config: self.read(i2c, Register::CONFIG_PORT)?,
+            output_drive_0: self.read(i2c, Register::OUTPUT_DRIVE_0)?.bits,
+            output_drive_1: self.read(i2c, Register::OUTPUT_DRIVE_1)?.bits,
+            input_latch: self.read(i2c, Register::INPUT_LATCH)?,
+            pull_enable: self.read(i2c, Register::PULLUPDOWN_EN)?,
+            pull_select: self.read(i2c, Register::PULLUPDOWN_SEL)?,
+            interrupt_mask: self.read(i2c, Register::INTERRUPT_MASK)?,
+            interrupt_status: self.read(i2c, Register::INTERRUPT_STATUS)?,
+            output_port_config: self.read(i2c, Register::OUTPUT_PORT_CONFIG)?.bits,
+        })
+    }
+
+    /// Format a register dump into `buf` without allocating and return the number of bytes
+    /// written.  Returns `Error::BufferTooSmall` if the dump does not fit.
+    pub fn dump_to(&self, i2c: &mut T, buf: &mut [u8]) -> Result<usize, Error<E>> {
+        let dump = self.dump(i2c)?;
+        let mut writer = dump::SliceWriter::new(buf);
+        write!(writer, "{}", dump).map_err(|_| Error::BufferTooSmall)?;
+        Ok(writer.len())
+    }
+
+    /// Read every writable register into a `Configuration` snapshot.
+    pub fn read_configuration(&self, i2c: &mut T) -> Result<Configuration, E> {
+        let drive_0 = self.read(i2c, Register::OUTPUT_DRIVE_0)?.bits;
+        let drive_1 = self.read(i2c, Register::OUTPUT_DRIVE_1)?.bits;
+        Ok(Configuration {
+            outputs: self.read(i2c, Register::OUTPUT_PORT)?,
+            polarity: self.read(i2c, Register::POLARITY_INVERSION)?,
+            config: self.read(i2c, Register::CONFIG_PORT)?,
+            output_drive: u16::from_le_bytes([drive_0, drive_1]),
+            input_latch: self.read(i2c, Register::INPUT_LATCH)?,
+            pull_enable: self.read(i2c, Register::PULLUPDOWN_EN)?,
+            pull_select: self.read(i2c, Register::PULLUPDOWN_SEL)?,
+            interrupt_mask: self.read(i2c, Register::INTERRUPT_MASK)?,
+            output_port_config: self.read(i2c, Register::OUTPUT_PORT_CONFIG)?.bits,
+        })
+    }
+
+    /// Read the Output Port Configuration register as one `OutputMode` per pin, P00 first.
+    ///
+    /// The PCAL9554B only selects the output stage for the whole port, with bit 0 (ODEN); the
+    /// other bits are reserved and ignored.  All eight entries are therefore always equal on this
+    /// part.
+    pub fn read_output_modes(&self, i2c: &mut T) -> Result<[OutputMode; 8], E> {
+        let config = self.read(i2c, Register::OUTPUT_PORT_CONFIG)?;
+        let mode = if config.contains(Port::P00) {
+            OutputMode::OpenDrain
+        } else {
+            OutputMode::PushPull
+        };
+        Ok([mode; 8])
+    }
+
+    /// Classify how the device is driving `pin`, which should be a single pin.
+    ///
+    /// This is derived from the registers only, not measured: an input is `HighZ`, an output
+    /// whose flip-flop is 0 is `Low`, and an output whose flip-flop is 1 is `High` in push-pull
+    /// mode and `HighZ` in open-drain mode.  The level actually seen on a `HighZ` pin depends on
+    /// external pull resistors and other drivers on the line, and the internal pull resistor is
+    /// not taken into account.
+    ///
+    /// The Output Port and Output Port Configuration registers are only read when they decide
+    /// the result.
+    pub fn pin_level(&self, i2c: &mut T, pin: Port) -> Result<PinLevel, E> {
+        if self.read_config(i2c)?.contains(pin) {
+            return Ok(PinLevel::HighZ);
+        }
+        if !self.read_outputs(i2c)?.contains(pin) {
+            return Ok(PinLevel::Low);
+        }
+        match self.read_output_modes(i2c)?[0] {
+            OutputMode::PushPull => Ok(PinLevel::High),
+            OutputMode::OpenDrain => Ok(PinLevel::HighZ),
+        }
+    }
+
+    /// Read the Input Latch and Interrupt Mask registers and return `(input_latch, mask)`.
+    pub fn interrupt_config(&self, i2c: &mut T) -> Result<(Port, Port), E> {
+        let latch = self.read(i2c, Register::INPUT_LATCH)?;
+        let mask = self.read_interrupt_mask(i2c)?;
+        Ok((latch, mask))
+    }
+}
+
+impl<T, E, V> PCA9554<T, V>
+where
+    T: Write<Error = E>,
+    V: Extended,
+{
+    /// Set the Interrupt Mask register.  Pins set to 1 are masked and do not generate an
+    /// interrupt, pins set to 0 do.
+    pub fn write_interrupt_mask(&self, i2c: &mut T, mask: Port) -> Result<(), E> {
+        self.write(i2c, Register::INTERRUPT_MASK, mask)
+    }
+
+    /// Write every writable register from a `Configuration` snapshot.
+    ///
+    /// The Output Port register is written before the Configuration register so that pins turned
+    /// into outputs immediately present the requested level.
+    pub fn write_configuration(&self, i2c: &mut T, config: &Configuration) -> Result<(), E> {
+        let drive = config.output_drive.to_le_bytes();
+        self.write(
+            i2c,
+            Register::OUTPUT_DRIVE_0,
+            Port::from_bits_truncate(drive[0]),
+        )?;
+        self.write(
+            i2c,
+            Register::OUTPUT_DRIVE_1,
+            Port::from_bits_truncate(drive[1]),
+        )?;
+        self.write(
+            i2c,
+            Register::OUTPUT_PORT_CONFIG,
+            Port::from_bits_truncate(config.output_port_config),
+        )?;
+        self.write(i2c, Register::PULLUPDOWN_SEL, config.pull_select)?;
+        self.write(i2c, Register::PULLUPDOWN_EN, config.pull_enable)?;
+        self.write(i2c, Register::POLARITY_INVERSION, config.polarity)?;
+        self.write(i2c, Register::INPUT_LATCH, config.input_latch)?;
+        self.write(i2c, Register::INTERRUPT_MASK, config.interrupt_mask)?;
+        self.write(i2c, Register::OUTPUT_PORT, config.outputs)?;
+        self.write(i2c, Register::CONFIG_PORT, config.config)
+    }
+
+    /// Mask the interrupts of all pins.
+    pub fn mask_all_interrupts(&self, i2c: &mut T) -> Result<(), E> {
+        self.write_interrupt_mask(i2c, Port::all())
+    }
+
+    /// Unmask the interrupts of all pins.
+    pub fn unmask_all_interrupts(&self, i2c: &mut T) -> Result<(), E> {
+        self.write_interrupt_mask(i2c, Port::empty())
+    }
+
+    /// Set up the device for eight push buttons to ground: every pin an input with its pull-up
+    /// enabled, no polarity inversion and every interrupt unmasked.
+    ///
+    /// The pull-ups are selected and enabled before the Configuration register is written, so
+    /// the pins never float.
+    pub fn init_button_board(&self, i2c: &mut T) -> Result<(), E> {
+        self.write(i2c, Register::PULLUPDOWN_SEL, Port::all())?;
+        self.write(i2c, Register::PULLUPDOWN_EN, Port::all())?;
+        self.write(i2c, Register::CONFIG_PORT, Port::all())?;
+        self.write(i2c, Register::POLARITY_INVERSION, Port::empty())?;
+        self.write_interrupt_mask(i2c, Port::empty())
+    }
+}
+
+impl<T, E, V> PCA9554<T, V>
+where
+    T: WriteRead<Error = E> + Write<Error = E>,
+    V: Extended,
+{
+    /// Configure `pins` as inputs and unmask their interrupts, masking interrupts on every other
+    /// pin.  The direction of pins outside `pins` is left unchanged.
+    pub fn watch_pins(&self, i2c: &mut T, pins: Port) -> Result<(), E> {
+        self.modify(i2c, Register::CONFIG_PORT, |config| config | pins)?;
+        self.write_interrupt_mask(i2c, !pins)
+    }
+
+    /// Configure `pins` as inputs with the given pull resistor setting.  Other pins are left
+    /// unchanged.
+    ///
+    /// The pull resistor is selected and enabled before the pins are switched to inputs.  This way
+    /// the pull resistor is already in place when the output driver is released and the pins
+    /// never float in between.
+    pub fn configure_input(&self, i2c: &mut T, pins: Port, pull: PullMode) -> Result<(), E> {
+        match pull {
+            PullMode::None => {
+                self.modify(i2c, Register::PULLUPDOWN_EN, |enabled| enabled - pins)?;
+            }
+            PullMode::Up => {
+                self.modify(i2c, Register::PULLUPDOWN_SEL, |select| select | pins)?;
+                self.modify(i2c, Register::PULLUPDOWN_EN, |enabled| enabled | pins)?;
+            }
+            PullMode::Down => {
+                self.modify(i2c, Register::PULLUPDOWN_SEL, |select| select - pins)?;
+                self.modify(i2c, Register::PULLUPDOWN_EN, |enabled| enabled | pins)?;
+            }
+        }
+        self.modify(i2c, Register::CONFIG_PORT, |config| config | pins)?;
+        Ok(())
+    }
+
+    /// Apply `config`, read all registers back and return the observed configuration.
+    ///
+    /// Returns `Error::Mismatch` if the read-back differs from `config`, which usually points at
+    /// a wiring problem or a wrong address.
+    pub fn init_and_report(
+        &self,
+        i2c: &mut T,
+        config: &Configuration,
+    ) -> Result<Configuration, Error<E>> {
+        self.write_configuration(i2c, config)?;
+        let observed = self.read_configuration(i2c)?;
+        if observed != *config {
+            return Err(Error::Mismatch);
+        }
+        Ok(observed)
+    }
+
+    /// Read the Input Port register, which clears a pending interrupt, then unmask the interrupts
+    /// of `pins` only.  Returns the inputs read.
+    ///
+    /// Unlike `watch_pins()`, the pin directions are left alone.
+    pub fn rearm_watch(&self, i2c: &mut T, pins: Port) -> Result<Port, E> {
+        let inputs = self.read_inputs(i2c)?;
+        self.write_interrupt_mask(i2c, !pins)?;
+        Ok(inputs)
+    }
+
+    /// Make a best-effort check that the device at the address is a PCAL9554B rather than
+    /// another expander in the same address range, such as a PCF8574 or MCP23008.
+    ///
+    /// This is a heuristic, not a proof.  The reserved bits 7–1 of the Output Port Configuration
+    /// register must read 0, and inverting the Polarity Inversion register must read back as
+    /// written.  The original polarity is restored afterwards, also when flipping or reading it
+    /// back fails; the first error is returned.  On a part without these registers the writes may
+    /// briefly change its outputs or settings, so only run this before the board is in use.
+    pub fn identify(&self, i2c: &mut T) -> Result<bool, E> {
+        let output_config = self.read(i2c, Register::OUTPUT_PORT_CONFIG)?;
+        if output_config.bits & 0xFE != 0 {
+            return Ok(false);
+        }
+        let polarity = self.is_inverted(i2c)?;
+        let read_back = self
+            .set_inverted(i2c, !polarity)
+            .and_then(|()| self.is_inverted(i2c));
+        let restored = self.set_inverted(i2c, polarity);
+        let read_back = read_back?;
+        restored?;
+        Ok(read_back == !polarity)
+    }
+}
+
+/// Write the Output Port register of several devices sharing a bus, back to back in slice order,
+/// to update them as close to simultaneously as the bus allows.  Stops at the first failing
+/// write.
+///
+/// Each device gets its own write transaction.  embedded-hal 0.2 cannot chain writes to
+/// different addresses with repeated starts, so a stop condition separates them.
+pub fn write_outputs_fan_out<T, E>(i2c: &mut T, updates: &[(Address, Port)]) -> Result<(), E>
+where
+    T: Write<Error = E>,
+{
+    for &(address, outputs) in updates {
+        i2c.write(address as u8, &[Register::OUTPUT_PORT as u8, outputs.bits])?;
+    }
+    Ok(())
+}
+
+/// Read the Input Port register of each device in `addrs`, in order, into the matching element of
+/// `out`.  Stops at the first failing read.  If the slices differ in length, the surplus elements
+/// of the longer one are ignored.
+pub fn read_inputs_all<T, E>(i2c: &mut T, addrs: &[Address], out: &mut [Port]) -> Result<(), E>
+where
+    T: WriteRead<Error = E>,
+{
+    for (&address, inputs) in addrs.iter().zip(out.iter_mut()) {
+        let mut buffer = [0u8; 1];
+        i2c.write_read(address as u8, &[Register::INPUT_PORT as u8], &mut buffer)?;
+        *inputs = Port::from(buffer[0]);
+    }
+    Ok(())
+}
+
+/// Valid addresses for the PCA9554 family: 0x20–0x27 for the PCA9554 and PCAL9554B, 0x38–0x3F
+/// for the PCA9554A and TCA9554A.
+#[allow(non_camel_case_types)]
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Address {
     ADDR_0x20 = 0x20,
     ADDR_0x21 = 0x21,
     ADDR_0x22 = 0x22,
@@ -125,6 +1557,28 @@ pub enum Address {
     ADDR_0x25 = 0x25,
     ADDR_0x26 = 0x26,
     ADDR_0x27 = 0x27,
+    ADDR_0x38 = 0x38,
+    ADDR_0x39 = 0x39,
+    ADDR_0x3A = 0x3A,
+    ADDR_0x3B = 0x3B,
+    ADDR_0x3C = 0x3C,
+    ADDR_0x3D = 0x3D,
+    ADDR_0x3E = 0x3E,
+    ADDR_0x3F = 0x3F,
+}
+
+impl Address {
+    /// Classify an address found on the bus, e.g. while scanning.  Returns `None` for addresses
+    /// outside both address ranges.
+    pub fn classify(addr: u8) -> Option<(Family, Address)> {
+        let address = Address::try_from(addr).ok()?;
+        let family = if addr < 0x38 {
+            Family::Pca9554
+        } else {
+            Family::Pca9554a
+        };
+        Some((family, address))
+    }
 }
 
 impl TryFrom<u8> for Address {
@@ -139,13 +1593,54 @@ impl TryFrom<u8> for Address {
             0x25 => Ok(Address::ADDR_0x25),
             0x26 => Ok(Address::ADDR_0x26),
             0x27 => Ok(Address::ADDR_0x27),
+            0x38 => Ok(Address::ADDR_0x38),
+            0x39 => Ok(Address::ADDR_0x39),
+            0x3A => Ok(Address::ADDR_0x3A),
+            0x3B => Ok(Address::ADDR_0x3B),
+            0x3C => Ok(Address::ADDR_0x3C),
+            0x3D => Ok(Address::ADDR_0x3D),
+            0x3E => Ok(Address::ADDR_0x3E),
+            0x3F => Ok(Address::ADDR_0x3F),
             _ => Err(()),
         }
     }
 }
 
+/// The physical output level that switches a load on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ActiveLevel {
+    High,
+    Low,
+}
+
+/// Pull resistor setting of an input pin.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PullMode {
+    None,
+    Up,
+    Down,
+}
+
+/// What the device does to a pin, as classified by `PCA9554::pin_level()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PinLevel {
+    /// Actively driven low.
+    Low,
+    /// Actively driven high.
+    High,
+    /// Not driven: an input, or an open-drain output released to high impedance.
+    HighZ,
+}
+
+/// Output stage of an output pin.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OutputMode {
+    PushPull,
+    OpenDrain,
+}
+
 #[allow(non_camel_case_types)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Register {
     INPUT_PORT = 0x00,
     OUTPUT_PORT = 0x01,
@@ -161,10 +1656,27 @@ pub enum Register {
     OUTPUT_PORT_CONFIG = 0x4F,
 }
 
+impl Register {
+    /// The register address, for tooling that needs the raw value.
+    pub const fn addr(self) -> u8 {
+        self as u8
+    }
+
+    /// Whether this is one of the extended registers at 0x40–0x4F.
+    pub const fn is_extended(self) -> bool {
+        self as u8 >= 0x40
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use core::convert::Infallible;
     use embedded_hal_mock::i2c::{Mock, Transaction};
+    use embedded_hal_mock::MockError;
+    use std::cell::{Cell, RefCell};
+    use std::io::ErrorKind;
+    use std::rc::Rc;
 
     #[test]
     fn test_read_inputs() {
@@ -185,7 +1697,7 @@ mod tests {
     #[test]
     fn test_read_empty() {
         let addr = Address::ADDR_0x24;
-        let raw_response_value = vec![0, 0];
+        let raw_response_value = vec![0];
         let expected = [Transaction::write_read(
             addr as u8,
             vec![Register::INPUT_PORT as u8],
@@ -202,7 +1714,7 @@ mod tests {
     #[test]
     fn test_read_outputs() {
         let addr = Address::ADDR_0x22;
-        let raw_response_value = vec![0xAA, 0x55];
+        let raw_response_value = vec![0xAA];
         let expected = [Transaction::write_read(
             addr as u8,
             vec![Register::OUTPUT_PORT as u8],
@@ -218,4 +1730,1864 @@ mod tests {
         let result = device.read_outputs(&mut i2c).unwrap();
         assert_eq!(result, expected_result);
     }
+
+    #[test]
+    fn test_new_raw_address() {
+        let expected = [Transaction::write_read(
+            0x30,
+            vec![Register::INPUT_PORT as u8],
+            vec![0b0000_0001],
+        )];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new_raw_address(&i2c, 0x30);
+        assert_eq!(device.address(), None);
+        assert_eq!(device.read_inputs(&mut i2c).unwrap(), Port::P00);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_highest_priority_interrupt() {
+        let addr = Address::ADDR_0x20;
+        let status_read = |value| {
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::INTERRUPT_STATUS as u8],
+                vec![value],
+            )
+        };
+        let expected = [status_read(0b0010_1000), status_read(0)];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(
+            device.highest_priority_interrupt(&mut i2c).unwrap(),
+            Some(3)
+        );
+        assert_eq!(device.highest_priority_interrupt(&mut i2c).unwrap(), None);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_port_macro() {
+        assert_eq!(port!(0, 3, 7), Port::P00 | Port::P03 | Port::P07);
+        assert_eq!(port!(), Port::empty());
+        assert_eq!(port!(0, 1, 2, 3, 4, 5, 6, 7,), Port::all());
+    }
+
+    #[test]
+    fn test_watch_pins() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::CONFIG_PORT as u8],
+                vec![0b0000_0001],
+            ),
+            Transaction::write(addr as u8, vec![Register::CONFIG_PORT as u8, 0b0000_1101]),
+            Transaction::write(
+                addr as u8,
+                vec![Register::INTERRUPT_MASK as u8, 0b1111_0011],
+            ),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device.watch_pins(&mut i2c, Port::P02 | Port::P03).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_modify_outputs() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::OUTPUT_PORT as u8],
+                vec![0b1000_0001],
+            ),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0000_0111]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let result = device
+            .modify_outputs(&mut i2c, |outputs| {
+                (outputs | Port::P01 | Port::P02) - Port::P07
+            })
+            .unwrap();
+        assert_eq!(result, Port::P00 | Port::P01 | Port::P02);
+        i2c.done();
+    }
+
+    /// Transactions of a read-modify-write of `reg` reading `read` and writing `written`.
+    pub(crate) fn rmw(addr: Address, reg: Register, read: u8, written: u8) -> [Transaction; 2] {
+        [
+            Transaction::write_read(addr as u8, vec![reg as u8], vec![read]),
+            Transaction::write(addr as u8, vec![reg as u8, written]),
+        ]
+    }
+
+    #[test]
+    fn test_or_outputs() {
+        let addr = Address::ADDR_0x20;
+        let expected = rmw(addr, Register::OUTPUT_PORT, 0b1100_1100, 0b1100_1111);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let result = device.or_outputs(&mut i2c, Port::P00 | Port::P01).unwrap();
+        assert_eq!(result.bits(), 0b1100_1111);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_and_outputs() {
+        let addr = Address::ADDR_0x20;
+        let expected = rmw(addr, Register::OUTPUT_PORT, 0b1100_1100, 0b0000_1100);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let keep = Port::from_bits_truncate(0b0000_1111);
+        let result = device.and_outputs(&mut i2c, keep).unwrap();
+        assert_eq!(result.bits(), 0b0000_1100);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_xor_outputs() {
+        let addr = Address::ADDR_0x20;
+        let expected = rmw(addr, Register::OUTPUT_PORT, 0b1100_1100, 0b0011_1100);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let flip = Port::from_bits_truncate(0b1111_0000);
+        let result = device.xor_outputs(&mut i2c, flip).unwrap();
+        assert_eq!(result.bits(), 0b0011_1100);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_invert_all_outputs() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::OUTPUT_PORT as u8],
+                vec![0b1100_0011],
+            ),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0011_1100]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let result = device.invert_all_outputs(&mut i2c).unwrap();
+        assert_eq!(result.bits(), 0b0011_1100);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_set_pin_index() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::OUTPUT_PORT as u8],
+                vec![0b0000_0001],
+            ),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0001_0001]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device.set_pin_index(&mut i2c, 4, true).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_set_pin() {
+        let addr = Address::ADDR_0x20;
+        let expected = rmw(addr, Register::OUTPUT_PORT, 0b1111_1111, 0b1111_0111);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device.set_pin(&mut i2c, PinId::P3, false).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_set_pin_index_out_of_range() {
+        let mut i2c = Mock::new(&[]);
+        let device = PCA9554::new(&i2c, Address::ADDR_0x20);
+        assert_eq!(
+            device.set_pin_index(&mut i2c, 8, true),
+            Err(Error::InvalidPin)
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_configure_input_pull_up() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::PULLUPDOWN_SEL as u8],
+                vec![0b0000_0000],
+            ),
+            Transaction::write(
+                addr as u8,
+                vec![Register::PULLUPDOWN_SEL as u8, 0b0000_0011],
+            ),
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::PULLUPDOWN_EN as u8],
+                vec![0b1000_0000],
+            ),
+            Transaction::write(addr as u8, vec![Register::PULLUPDOWN_EN as u8, 0b1000_0011]),
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::CONFIG_PORT as u8],
+                vec![0b0000_0000],
+            ),
+            Transaction::write(addr as u8, vec![Register::CONFIG_PORT as u8, 0b0000_0011]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device
+            .configure_input(&mut i2c, Port::P00 | Port::P01, PullMode::Up)
+            .unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_driven_low_count() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::OUTPUT_PORT as u8],
+                vec![0b1010_0101],
+            ),
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::CONFIG_PORT as u8],
+                vec![0b1111_0000],
+            ),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(device.driven_low_count(&mut i2c).unwrap(), 2);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_sinking_outputs() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::OUTPUT_PORT as u8],
+                vec![0b1010_0101],
+            ),
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::CONFIG_PORT as u8],
+                vec![0b1111_0000],
+            ),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(
+            device.sinking_outputs(&mut i2c).unwrap(),
+            Port::P01 | Port::P03
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_read_pin_logical_inverted() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write(
+                addr as u8,
+                vec![Register::POLARITY_INVERSION as u8, 0b0000_1000],
+            ),
+            // P03 is electrically low; the device reports it inverted.
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::INPUT_PORT as u8],
+                vec![0b0000_1000],
+            ),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device.set_inverted(&mut i2c, Port::P03).unwrap();
+        assert!(device.read_pin_logical(&mut i2c, Port::P03).unwrap());
+        i2c.done();
+    }
+
+    #[test]
+    fn test_is_pin_inverted() {
+        let addr = Address::ADDR_0x20;
+        let polarity_read = || {
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::POLARITY_INVERSION as u8],
+                vec![0b0001_0000],
+            )
+        };
+        let expected = [polarity_read(), polarity_read()];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert!(device.is_pin_inverted(&mut i2c, Port::P04).unwrap());
+        assert!(!device.is_pin_inverted(&mut i2c, Port::P00).unwrap());
+        i2c.done();
+    }
+
+    fn input_reads(addr: Address, values: &[u8]) -> Vec<Transaction> {
+        values
+            .iter()
+            .map(|&value| {
+                Transaction::write_read(addr as u8, vec![Register::INPUT_PORT as u8], vec![value])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_read_inputs_stable() {
+        let addr = Address::ADDR_0x20;
+        let expected = input_reads(addr, &[0x01, 0x03, 0x01, 0x07, 0x07, 0x07]);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let result = device.read_inputs_stable(&mut i2c, 8, 3).unwrap();
+        assert_eq!(result, Port::P00 | Port::P01 | Port::P02);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_read_inputs_unstable() {
+        let addr = Address::ADDR_0x20;
+        let expected = input_reads(addr, &[0x00, 0xFF, 0x00, 0xFF]);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let result = device.read_inputs_stable(&mut i2c, 4, 2);
+        assert_eq!(result, Err(Error::Unstable));
+        i2c.done();
+    }
+
+    #[test]
+    fn test_read_inputs_stable_per_pin() {
+        let addr = Address::ADDR_0x20;
+        // P00 keeps bouncing, P01 and P02 stay high and the other pins stay low.
+        let expected = input_reads(addr, &[0x06, 0x07, 0x06, 0x07]);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let result = device.read_inputs_stable(&mut i2c, 4, 3).unwrap();
+        assert_eq!(result, Port::P01 | Port::P02);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_bus_error_conversion() {
+        fn fallible() -> Result<(), Error<MockError>> {
+            Err(MockError::Io(ErrorKind::Other))?;
+            Ok(())
+        }
+
+        assert_eq!(fallible(), Err(Error::Bus(MockError::Io(ErrorKind::Other))));
+    }
+
+    #[cfg(feature = "core-error")]
+    #[test]
+    fn test_error_trait() {
+        let error: &dyn core::error::Error = &Error::<MockError>::InvalidPin;
+        assert_eq!(error.to_string(), "pin index out of range");
+    }
+
+    fn dump_transactions(addr: Address) -> Vec<Transaction> {
+        [
+            (Register::INPUT_PORT, 0x12),
+            (Register::OUTPUT_PORT, 0xFF),
+            (Register::POLARITY_INVERSION, 0x00),
+            (Register::CONFIG_PORT, 0xF0),
+            (Register::OUTPUT_DRIVE_0, 0xFF),
+            (Register::OUTPUT_DRIVE_1, 0xFF),
+            (Register::INPUT_LATCH, 0x00),
+            (Register::PULLUPDOWN_EN, 0x0F),
+            (Register::PULLUPDOWN_SEL, 0xFF),
+            (Register::INTERRUPT_MASK, 0xFE),
+            (Register::INTERRUPT_STATUS, 0x01),
+            (Register::OUTPUT_PORT_CONFIG, 0x00),
+        ]
+        .iter()
+        .map(|&(reg, value)| Transaction::write_read(addr as u8, vec![reg as u8], vec![value]))
+        .collect()
+    }
+
+    #[test]
+    fn test_dump_to() {
+        let addr = Address::ADDR_0x20;
+        let expected = dump_transactions(addr);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let mut buf = [0u8; 128];
+        let len = device.dump_to(&mut i2c, &mut buf).unwrap();
+        assert_eq!(
+            &buf[..len],
+            &b"in=12 out=FF pol=00 cfg=F0 drv0=FF drv1=FF latch=00 puen=0F pusel=FF \
+               intmask=FE intstat=01 outcfg=00"[..]
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_dump_to_buffer_too_small() {
+        let addr = Address::ADDR_0x20;
+        let expected = dump_transactions(addr);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let mut buf = [0u8; 16];
+        let result = device.dump_to(&mut i2c, &mut buf);
+        assert_eq!(result, Err(Error::BufferTooSmall));
+        i2c.done();
+    }
+
+    /// Transaction of a single read of `reg` returning `value`.
+    fn read_tx(addr: Address, reg: Register, value: u8) -> Transaction {
+        Transaction::write_read(addr as u8, vec![reg as u8], vec![value])
+    }
+
+    #[test]
+    fn test_config_matches() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::CONFIG_PORT, 0b1111_0000),
+            read_tx(addr, Register::CONFIG_PORT, 0b1111_0001),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let config = Port::from_bits_truncate(0b1111_0000);
+        assert!(device.config_matches(&mut i2c, config).unwrap());
+        assert!(!device.config_matches(&mut i2c, config).unwrap());
+        i2c.done();
+    }
+
+    #[test]
+    fn test_interrupt_state() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::INTERRUPT_MASK, 0b1111_0000),
+            read_tx(addr, Register::INTERRUPT_STATUS, 0b0000_0100),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let (mask, status) = device.interrupt_state(&mut i2c).unwrap();
+        assert_eq!(mask.bits(), 0b1111_0000);
+        assert_eq!(status, Port::P02);
+        i2c.done();
+    }
+
+    /// Bus wrapper recording the order of bus and delay calls in a shared log.
+    struct LoggedBus {
+        inner: Mock,
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Write for LoggedBus {
+        type Error = MockError;
+
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), MockError> {
+            self.log
+                .borrow_mut()
+                .push(format!("write {:02X}", bytes[0]));
+            self.inner.write(address, bytes)
+        }
+    }
+
+    impl WriteRead for LoggedBus {
+        type Error = MockError;
+
+        fn write_read(
+            &mut self,
+            address: u8,
+            bytes: &[u8],
+            buffer: &mut [u8],
+        ) -> Result<(), MockError> {
+            self.log.borrow_mut().push(format!("read {:02X}", bytes[0]));
+            self.inner.write_read(address, bytes, buffer)
+        }
+    }
+
+    struct LoggedDelay {
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl DelayUs<u32> for LoggedDelay {
+        fn delay_us(&mut self, us: u32) {
+            self.log.borrow_mut().push(format!("delay {}", us));
+        }
+    }
+
+    #[test]
+    fn test_pulse_pin_timed() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::OUTPUT_PORT, 0b1000_0000),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b1000_0010]),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b1000_0000]),
+        ];
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut i2c = LoggedBus {
+            inner: Mock::new(&expected),
+            log: log.clone(),
+        };
+        let mut delay = LoggedDelay { log: log.clone() };
+        let device = PCA9554::new(&i2c, addr);
+        device
+            .pulse_pin_timed(&mut i2c, &mut delay, Port::P01, true, 150)
+            .unwrap();
+        assert_eq!(
+            *log.borrow(),
+            ["read 01", "write 01", "delay 150", "write 01"]
+        );
+        i2c.inner.done();
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_port_from_str() {
+        assert_eq!("P00|P07".parse::<Port>(), Ok(Port::P00 | Port::P07));
+        assert_eq!(" P03 | P04 ".parse::<Port>(), Ok(Port::P03 | Port::P04));
+        assert_eq!("".parse::<Port>(), Ok(Port::empty()));
+        assert_eq!("P00|P09".parse::<Port>(), Err(ParsePortError));
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let addr = Address::ADDR_0x20;
+        let mut expected = Vec::new();
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b0000_0001, 0b0100_0001));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b0100_0001, 0b0000_0001));
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let mut state = false;
+        device.heartbeat(&mut i2c, Port::P06, &mut state).unwrap();
+        assert!(state);
+        device.heartbeat(&mut i2c, Port::P06, &mut state).unwrap();
+        assert!(!state);
+        i2c.done();
+    }
+
+    fn configuration_writes(addr: Address, config: &Configuration) -> Vec<Transaction> {
+        let drive = config.output_drive.to_le_bytes();
+        [
+            (Register::OUTPUT_DRIVE_0, drive[0]),
+            (Register::OUTPUT_DRIVE_1, drive[1]),
+            (Register::OUTPUT_PORT_CONFIG, config.output_port_config),
+            (Register::PULLUPDOWN_SEL, config.pull_select.bits()),
+            (Register::PULLUPDOWN_EN, config.pull_enable.bits()),
+            (Register::POLARITY_INVERSION, config.polarity.bits()),
+            (Register::INPUT_LATCH, config.input_latch.bits()),
+            (Register::INTERRUPT_MASK, config.interrupt_mask.bits()),
+            (Register::OUTPUT_PORT, config.outputs.bits()),
+            (Register::CONFIG_PORT, config.config.bits()),
+        ]
+        .iter()
+        .map(|&(reg, value)| Transaction::write(addr as u8, vec![reg as u8, value]))
+        .collect()
+    }
+
+    fn configuration_reads(addr: Address, config: &Configuration) -> Vec<Transaction> {
+        let drive = config.output_drive.to_le_bytes();
+        [
+            (Register::OUTPUT_DRIVE_0, drive[0]),
+            (Register::OUTPUT_DRIVE_1, drive[1]),
+            (Register::OUTPUT_PORT, config.outputs.bits()),
+            (Register::POLARITY_INVERSION, config.polarity.bits()),
+            (Register::CONFIG_PORT, config.config.bits()),
+            (Register::INPUT_LATCH, config.input_latch.bits()),
+            (Register::PULLUPDOWN_EN, config.pull_enable.bits()),
+            (Register::PULLUPDOWN_SEL, config.pull_select.bits()),
+            (Register::INTERRUPT_MASK, config.interrupt_mask.bits()),
+            (Register::OUTPUT_PORT_CONFIG, config.output_port_config),
+        ]
+        .iter()
+        .map(|&(reg, value)| read_tx(addr, reg, value))
+        .collect()
+    }
+
+    #[test]
+    fn test_init_and_report() {
+        let addr = Address::ADDR_0x20;
+        let config = Configuration {
+            config: Port::P00,
+            ..Configuration::default()
+        };
+        let mut expected = configuration_writes(addr, &config);
+        expected.extend(configuration_reads(addr, &config));
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(device.init_and_report(&mut i2c, &config), Ok(config));
+        i2c.done();
+    }
+
+    #[test]
+    fn test_init_and_report_mismatch() {
+        let addr = Address::ADDR_0x20;
+        let config = Configuration {
+            config: Port::P00,
+            ..Configuration::default()
+        };
+        let observed = Configuration {
+            config: Port::all(),
+            ..config
+        };
+        let mut expected = configuration_writes(addr, &config);
+        expected.extend(configuration_reads(addr, &observed));
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(
+            device.init_and_report(&mut i2c, &config),
+            Err(Error::Mismatch)
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_read_input_checked() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::CONFIG_PORT, 0b0000_0100),
+            read_tx(addr, Register::INPUT_PORT, 0b0000_0100),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(device.read_input_checked(&mut i2c, Port::P02), Ok(true));
+        i2c.done();
+    }
+
+    #[test]
+    fn test_read_input_checked_output_pin() {
+        let addr = Address::ADDR_0x20;
+        let expected = [read_tx(addr, Register::CONFIG_PORT, 0b1111_0000)];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(
+            device.read_input_checked(&mut i2c, Port::P01),
+            Err(Error::NotAnInput)
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_reverse_bits() {
+        assert_eq!(Port::P00.reverse_bits(), Port::P07);
+        assert_eq!(
+            (Port::P01 | Port::P04).reverse_bits(),
+            Port::P06 | Port::P03
+        );
+        assert_eq!(Port::all().reverse_bits(), Port::all());
+    }
+
+    #[test]
+    fn test_write_outputs_to_configured() {
+        let addr = Address::ADDR_0x20;
+        let mut expected = vec![read_tx(addr, Register::CONFIG_PORT, 0b1111_0000)];
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1010_0000, 0b1010_0101));
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let value = Port::from_bits_truncate(0b0101_0101);
+        let result = device.write_outputs_to_configured(&mut i2c, value).unwrap();
+        assert_eq!(result.bits(), 0b1010_0101);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_pin_id_conversions() {
+        assert_eq!(PinId::from_index(5), Some(PinId::P5));
+        assert_eq!(PinId::from_index(8), None);
+        assert_eq!(PinId::P5.index(), 5);
+        assert_eq!(PinId::P5.to_port(), Port::P05);
+        assert_eq!(Port::from(PinId::P0), Port::P00);
+        for index in 0..8 {
+            assert_eq!(PinId::from_index(index).unwrap().index(), index);
+        }
+    }
+
+    /// INT line that reads high a given number of times before asserting.
+    struct IntPin {
+        high_reads: Cell<u32>,
+    }
+
+    impl InputPin for IntPin {
+        type Error = Infallible;
+
+        fn is_high(&self) -> Result<bool, Infallible> {
+            self.is_low().map(|low| !low)
+        }
+
+        fn is_low(&self) -> Result<bool, Infallible> {
+            let remaining = self.high_reads.get();
+            self.high_reads.set(remaining.saturating_sub(1));
+            Ok(remaining == 0)
+        }
+    }
+
+    #[test]
+    fn test_wait_and_service() {
+        let addr = Address::ADDR_0x20;
+        let expected = [read_tx(addr, Register::INPUT_PORT, 0b0000_0010)];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let int_pin = IntPin {
+            high_reads: Cell::new(3),
+        };
+        assert_eq!(
+            device.wait_and_service(&mut i2c, &int_pin).unwrap(),
+            Port::P01
+        );
+        assert_eq!(int_pin.high_reads.get(), 0);
+        i2c.done();
+    }
+
+    /// INT line whose reads always fail.
+    struct BrokenPin;
+
+    impl InputPin for BrokenPin {
+        type Error = ();
+
+        fn is_high(&self) -> Result<bool, ()> {
+            Err(())
+        }
+
+        fn is_low(&self) -> Result<bool, ()> {
+            Err(())
+        }
+    }
+
+    #[test]
+    fn test_wait_and_service_pin_error() {
+        let mut i2c = Mock::new(&[]);
+        let device = PCA9554::new(&i2c, Address::ADDR_0x20);
+        assert_eq!(
+            device.wait_and_service(&mut i2c, &BrokenPin),
+            Err(WaitError::Pin(()))
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_core_snapshot_round_trip() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::INPUT_PORT, 0x5A),
+            read_tx(addr, Register::OUTPUT_PORT, 0xF0),
+            read_tx(addr, Register::POLARITY_INVERSION, 0x01),
+            read_tx(addr, Register::CONFIG_PORT, 0x0F),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0xF0]),
+            Transaction::write(addr as u8, vec![Register::POLARITY_INVERSION as u8, 0x01]),
+            Transaction::write(addr as u8, vec![Register::CONFIG_PORT as u8, 0x0F]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let snapshot = device.core_snapshot(&mut i2c).unwrap();
+        assert_eq!(snapshot, [0x5A, 0xF0, 0x01, 0x0F]);
+        device.core_restore(&mut i2c, &snapshot).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_variant_rejects_extended_register() {
+        let addr = Address::ADDR_0x20;
+        let expected = [read_tx(addr, Register::CONFIG_PORT, 0xFF)];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::with_variant(&i2c, addr, variant::Pca9554);
+        assert_eq!(
+            device.read_register(&mut i2c, Register::INTERRUPT_MASK),
+            Err(Error::UnsupportedByVariant)
+        );
+        assert_eq!(
+            device.write_register(&mut i2c, Register::PULLUPDOWN_EN, Port::all()),
+            Err(Error::UnsupportedByVariant)
+        );
+        assert_eq!(
+            device.read_register(&mut i2c, Register::CONFIG_PORT),
+            Ok(Port::all())
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_default_variant_accepts_extended_register() {
+        let addr = Address::ADDR_0x20;
+        let expected = [Transaction::write(
+            addr as u8,
+            vec![Register::PULLUPDOWN_EN as u8, 0x0F],
+        )];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let value = Port::from_bits_truncate(0x0F);
+        device
+            .write_register(&mut i2c, Register::PULLUPDOWN_EN, value)
+            .unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_sample_inputs() {
+        let addr = Address::ADDR_0x20;
+        let expected = input_reads(addr, &[0x01, 0x02, 0x04, 0x08]);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let mut samples = [Port::empty(); 4];
+        device.sample_inputs(&mut i2c, &mut samples).unwrap();
+        assert_eq!(samples, [Port::P00, Port::P01, Port::P02, Port::P03]);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_set_pin_on_active_high() {
+        let addr = Address::ADDR_0x20;
+        let mut expected = Vec::new();
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b0000_0000, 0b0000_0001));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b0000_0001, 0b0000_0000));
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device.set_pin_on(&mut i2c, Port::P00).unwrap();
+        device.set_pin_off(&mut i2c, Port::P00).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_set_pin_on_active_low() {
+        let addr = Address::ADDR_0x20;
+        let mut expected = Vec::new();
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1111_1111, 0b1111_1110));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1111_1110, 0b1111_1111));
+
+        let mut i2c = Mock::new(&expected);
+        let mut device = PCA9554::new(&i2c, addr);
+        device.set_active_level(ActiveLevel::Low);
+        device.set_pin_on(&mut i2c, Port::P00).unwrap();
+        device.set_pin_off(&mut i2c, Port::P00).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_detect_stuck_all_identical() {
+        let addr = Address::ADDR_0x20;
+        let expected = input_reads(addr, &[0x5A; 5]);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(device.detect_stuck(&mut i2c, 5).unwrap(), Port::all());
+        i2c.done();
+    }
+
+    #[test]
+    fn test_detect_stuck_changing_pins() {
+        let addr = Address::ADDR_0x20;
+        let expected = input_reads(addr, &[0b0000_0001, 0b0000_0011, 0b1000_0001]);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let stuck = device.detect_stuck(&mut i2c, 3).unwrap();
+        assert_eq!(stuck, Port::all() - Port::P01 - Port::P07);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_timed_update() {
+        let addr = Address::ADDR_0x20;
+        let expected = input_reads(addr, &[0b0000_0101, 0b0000_0100]);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let mut tracker = InputTracker::new(Port::P00 | Port::P01);
+        let mut now = 1000;
+        let mut clock = || {
+            now += 10;
+            now
+        };
+
+        let events = device
+            .timed_update(&mut i2c, &mut tracker, &mut clock)
+            .unwrap();
+        assert_eq!(events[..], [(1, false, 1010), (2, true, 1010)]);
+        let events = device
+            .timed_update(&mut i2c, &mut tracker, &mut clock)
+            .unwrap();
+        assert_eq!(events[..], [(0, false, 1020)]);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_mask_all_interrupts() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write(addr as u8, vec![Register::INTERRUPT_MASK as u8, 0xFF]),
+            Transaction::write(addr as u8, vec![Register::INTERRUPT_MASK as u8, 0x00]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device.mask_all_interrupts(&mut i2c).unwrap();
+        device.unmask_all_interrupts(&mut i2c).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_read_inputs_into() {
+        let addr = Address::ADDR_0x20;
+        let expected = input_reads(addr, &[0b1000_0001]);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let mut buf = [0u8; 1];
+        let result = device.read_inputs_into(&mut i2c, &mut buf).unwrap();
+        assert_eq!(result, Port::P00 | Port::P07);
+        assert_eq!(buf, [0b1000_0001]);
+        i2c.done();
+    }
+
+    /// A bus implementing only `WriteRead`.
+    struct ReadOnlyBus(Mock);
+
+    impl WriteRead for ReadOnlyBus {
+        type Error = MockError;
+
+        fn write_read(
+            &mut self,
+            address: u8,
+            bytes: &[u8],
+            buffer: &mut [u8],
+        ) -> Result<(), MockError> {
+            self.0.write_read(address, bytes, buffer)
+        }
+    }
+
+    #[test]
+    fn test_read_only_bus() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::INPUT_PORT, 0b0000_0011),
+            read_tx(addr, Register::CONFIG_PORT, 0b1111_0000),
+        ];
+
+        let mut i2c = ReadOnlyBus(Mock::new(&expected));
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(device.read_inputs(&mut i2c).unwrap(), Port::P00 | Port::P01);
+        assert!(device
+            .config_matches(&mut i2c, Port::from_bits_truncate(0xF0))
+            .unwrap());
+        i2c.0.done();
+    }
+
+    #[test]
+    fn test_drive_pin_writes_output_before_config() {
+        let addr = Address::ADDR_0x20;
+        let mut expected = Vec::new();
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b0000_0000, 0b0000_0100));
+        expected.extend(rmw(addr, Register::CONFIG_PORT, 0b1111_1111, 0b1111_1011));
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device.drive_pin(&mut i2c, Port::P02, true).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_to_ascii_bits() {
+        let mut buf = [0u8; 8];
+        Port::from(0xB2).to_ascii_bits(&mut buf);
+        assert_eq!(&buf, b"10110010");
+        assert_eq!(u8::from(Port::from(0xB2)), 0xB2);
+    }
+
+    #[test]
+    fn test_chord_pressed() {
+        let addr = Address::ADDR_0x20;
+        let expected = input_reads(addr, &[0b0000_0101, 0b0000_0101]);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert!(device
+            .chord_pressed(&mut i2c, Port::P00 | Port::P02)
+            .unwrap());
+        assert!(!device
+            .chord_pressed(&mut i2c, Port::P00 | Port::P01)
+            .unwrap());
+        i2c.done();
+    }
+
+    #[test]
+    fn test_address_u8() {
+        let i2c = Mock::new(&[]);
+        let device = PCA9554::new(&i2c, Address::ADDR_0x24);
+        assert_eq!(device.address_u8(), 0x24);
+    }
+
+    #[test]
+    fn test_read_output_modes() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::OUTPUT_PORT_CONFIG, 0b1010_0101),
+            read_tx(addr, Register::OUTPUT_PORT_CONFIG, 0b1010_0100),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(
+            device.read_output_modes(&mut i2c).unwrap(),
+            [OutputMode::OpenDrain; 8]
+        );
+        assert_eq!(
+            device.read_output_modes(&mut i2c).unwrap(),
+            [OutputMode::PushPull; 8]
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_direction_delta() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::CONFIG_PORT, 0b1111_0000),
+            read_tx(addr, Register::CONFIG_PORT, 0b1111_0000),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let current = Port::from(0b1111_0000);
+        assert_eq!(
+            device.direction_delta(&mut i2c, current).unwrap(),
+            Port::empty()
+        );
+        assert_eq!(
+            device
+                .direction_delta(&mut i2c, current | Port::P00)
+                .unwrap(),
+            Port::P00
+        );
+        i2c.done();
+    }
+
+    /// A bus holding the Output Port register in memory, slow enough between the read and the
+    /// write of a read-modify-write that unguarded updates from several threads would race.
+    #[cfg(feature = "critical-section")]
+    #[derive(Clone)]
+    struct SlowRegisterBus(std::sync::Arc<std::sync::Mutex<u8>>);
+
+    #[cfg(feature = "critical-section")]
+    impl WriteRead for SlowRegisterBus {
+        type Error = Infallible;
+
+        fn write_read(&mut self, _: u8, _: &[u8], buffer: &mut [u8]) -> Result<(), Infallible> {
+            buffer[0] = *self.0.lock().unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "critical-section")]
+    impl Write for SlowRegisterBus {
+        type Error = Infallible;
+
+        fn write(&mut self, _: u8, bytes: &[u8]) -> Result<(), Infallible> {
+            *self.0.lock().unwrap() = bytes[1];
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "critical-section")]
+    #[test]
+    fn test_modify_outputs_atomic_loses_no_update() {
+        let bus = SlowRegisterBus(Default::default());
+        let threads: Vec<_> = (0..8)
+            .map(|index| {
+                let mut i2c = bus.clone();
+                std::thread::spawn(move || {
+                    let device = PCA9554::new(&i2c, Address::ADDR_0x20);
+                    let pin = Port::from(1 << index);
+                    device
+                        .modify_outputs_atomic(&mut i2c, |outputs| outputs | pin)
+                        .unwrap();
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(*bus.0.lock().unwrap(), 0xFF);
+    }
+
+    #[test]
+    fn test_nibbles() {
+        let port = Port::from(0x5A);
+        assert_eq!(port.high_nibble(), 0x5);
+        assert_eq!(port.low_nibble(), 0xA);
+        assert_eq!(Port::from_nibbles(0xA, 0x5), port);
+        assert_eq!(Port::from_nibbles(0xFA, 0x5), port);
+    }
+
+    #[test]
+    fn test_classify_address() {
+        assert_eq!(
+            Address::classify(0x22),
+            Some((Family::Pca9554, Address::ADDR_0x22))
+        );
+        assert_eq!(
+            Address::classify(0x3B),
+            Some((Family::Pca9554a, Address::ADDR_0x3B))
+        );
+        assert_eq!(Address::classify(0x30), None);
+    }
+
+    #[test]
+    fn test_increment_outputs_wraps() {
+        let addr = Address::ADDR_0x20;
+        let mut expected = Vec::new();
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0xFE, 0xFF));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0xFF, 0x00));
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(device.increment_outputs(&mut i2c).unwrap(), Port::all());
+        assert_eq!(device.increment_outputs(&mut i2c).unwrap(), Port::empty());
+        i2c.done();
+    }
+
+    #[test]
+    fn test_register_addr() {
+        assert_eq!(Register::CONFIG_PORT.addr(), 0x03);
+        assert_eq!(Register::OUTPUT_PORT_CONFIG.addr(), 0x4F);
+    }
+
+    #[test]
+    fn test_write_outputs_fan_out() {
+        let expected = [
+            Transaction::write(0x21, vec![Register::OUTPUT_PORT as u8, 0x01]),
+            Transaction::write(0x20, vec![Register::OUTPUT_PORT as u8, 0x02]),
+            Transaction::write(0x3F, vec![Register::OUTPUT_PORT as u8, 0x03]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        write_outputs_fan_out(
+            &mut i2c,
+            &[
+                (Address::ADDR_0x21, Port::from(0x01)),
+                (Address::ADDR_0x20, Port::from(0x02)),
+                (Address::ADDR_0x3F, Port::from(0x03)),
+            ],
+        )
+        .unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_read_configured_inputs() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::CONFIG_PORT, 0b1111_0000),
+            read_tx(addr, Register::INPUT_PORT, 0b1010_0101),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let inputs = device.read_configured_inputs(&mut i2c).unwrap();
+        assert_eq!(inputs, Port::P05 | Port::P07);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_write_register_port_or_u8() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write(
+                addr as u8,
+                vec![Register::OUTPUT_DRIVE_0 as u8, 0b0101_0101],
+            ),
+            Transaction::write(
+                addr as u8,
+                vec![Register::OUTPUT_DRIVE_1 as u8, 0b1010_1010],
+            ),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device
+            .write_register(&mut i2c, Register::OUTPUT_DRIVE_0, Port::from(0b0101_0101))
+            .unwrap();
+        device
+            .write_register(&mut i2c, Register::OUTPUT_DRIVE_1, 0b1010_1010u8)
+            .unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_lamp_test() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::OUTPUT_PORT, 0b0000_0110),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0000_0000]),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0000_0110]),
+        ];
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut i2c = LoggedBus {
+            inner: Mock::new(&expected),
+            log: log.clone(),
+        };
+        let mut delay = LoggedDelay { log: log.clone() };
+        let mut device = PCA9554::new(&i2c, addr);
+        device.set_active_level(ActiveLevel::Low);
+        device.lamp_test(&mut i2c, &mut delay, 500_000).unwrap();
+        assert_eq!(
+            *log.borrow(),
+            ["read 01", "write 01", "delay 500000", "write 01"]
+        );
+        i2c.inner.done();
+    }
+
+    #[test]
+    fn test_first_low_output() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::OUTPUT_PORT, 0b0000_0011),
+            read_tx(addr, Register::OUTPUT_PORT, 0b1111_1111),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(device.first_low_output(&mut i2c).unwrap(), Some(2));
+        assert_eq!(device.first_low_output(&mut i2c).unwrap(), None);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_try_single() {
+        assert_eq!(Port::P03.try_single(), Ok(Port::P03));
+        assert_eq!(Port::empty().try_single(), Err(Error::NotSinglePin));
+        assert_eq!(
+            (Port::P00 | Port::P07).try_single(),
+            Err(Error::NotSinglePin)
+        );
+    }
+
+    #[test]
+    fn test_set_inverted_array() {
+        let addr = Address::ADDR_0x20;
+        let expected = [Transaction::write(
+            addr as u8,
+            vec![Register::POLARITY_INVERSION as u8, 0b1010_1010],
+        )];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let arr = [false, true, false, true, false, true, false, true];
+        device.set_inverted_array(&mut i2c, arr).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_snapshot_diff() {
+        let before = IoSnapshot {
+            inputs: Port::P00,
+            outputs: Port::empty(),
+            config: Port::all(),
+            polarity: Port::empty(),
+        };
+        let after = IoSnapshot {
+            inputs: Port::P01,
+            config: Port::all() - Port::P07,
+            ..before
+        };
+
+        let diff = SnapshotDiff::between(&before, &after);
+        let registers: Vec<_> = diff.differences().iter().map(|d| d.register).collect();
+        assert_eq!(registers, [Register::INPUT_PORT, Register::CONFIG_PORT]);
+        assert_eq!(diff.differences()[0].changed(), Port::P00 | Port::P01);
+        assert_eq!(diff.differences()[1].changed(), Port::P07);
+        assert!(SnapshotDiff::between(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn test_set_bargraph() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0001_1111]),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0xFF]),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0x00]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device.set_bargraph(&mut i2c, 5).unwrap();
+        device.set_bargraph(&mut i2c, 10).unwrap();
+        device.set_bargraph(&mut i2c, 0).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_init_button_board() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write(addr as u8, vec![Register::PULLUPDOWN_SEL as u8, 0xFF]),
+            Transaction::write(addr as u8, vec![Register::PULLUPDOWN_EN as u8, 0xFF]),
+            Transaction::write(addr as u8, vec![Register::CONFIG_PORT as u8, 0xFF]),
+            Transaction::write(addr as u8, vec![Register::POLARITY_INVERSION as u8, 0x00]),
+            Transaction::write(addr as u8, vec![Register::INTERRUPT_MASK as u8, 0x00]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device.init_button_board(&mut i2c).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_frame_bytes() {
+        let i2c = Mock::new(&[]);
+        let device = PCA9554::new(&i2c, Address::ADDR_0x24);
+        assert_eq!(device.write_frame_byte(), 0x48);
+        assert_eq!(device.read_frame_byte(), 0x49);
+    }
+
+    #[test]
+    fn test_rearm_watch() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::INPUT_PORT, 0b0100_0001),
+            Transaction::write(
+                addr as u8,
+                vec![Register::INTERRUPT_MASK as u8, 0b1111_0011],
+            ),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let inputs = device.rearm_watch(&mut i2c, Port::P02 | Port::P03).unwrap();
+        assert_eq!(inputs, Port::P00 | Port::P06);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_pin_level_driven_low() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::CONFIG_PORT, 0b1111_1110),
+            read_tx(addr, Register::OUTPUT_PORT, 0b1111_1110),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(
+            device.pin_level(&mut i2c, Port::P00).unwrap(),
+            PinLevel::Low
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_pin_level_released_open_drain() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::CONFIG_PORT, 0b1111_1110),
+            read_tx(addr, Register::OUTPUT_PORT, 0b0000_0001),
+            read_tx(addr, Register::OUTPUT_PORT_CONFIG, 0b0000_0001),
+            read_tx(addr, Register::CONFIG_PORT, 0b1111_1110),
+            read_tx(addr, Register::OUTPUT_PORT, 0b0000_0001),
+            read_tx(addr, Register::OUTPUT_PORT_CONFIG, 0b0000_0000),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(
+            device.pin_level(&mut i2c, Port::P00).unwrap(),
+            PinLevel::HighZ
+        );
+        assert_eq!(
+            device.pin_level(&mut i2c, Port::P00).unwrap(),
+            PinLevel::High
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_probe_with_retry() {
+        let addr = Address::ADDR_0x20;
+        let nack = || {
+            read_tx(addr, Register::CONFIG_PORT, 0xFF).with_error(MockError::Io(ErrorKind::Other))
+        };
+        let expected = [
+            nack(),
+            nack(),
+            read_tx(addr, Register::CONFIG_PORT, 0xFF),
+            nack(),
+        ];
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut delay = LoggedDelay { log: log.clone() };
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert!(device
+            .probe_with_retry(&mut i2c, &mut delay, 5, 1000)
+            .unwrap());
+        assert_eq!(*log.borrow(), ["delay 1000", "delay 1000"]);
+        assert!(!device
+            .probe_with_retry(&mut i2c, &mut delay, 1, 1000)
+            .unwrap());
+        i2c.done();
+    }
+
+    #[test]
+    fn test_read_field() {
+        let addr = Address::ADDR_0x20;
+        let expected = input_reads(addr, &[0b1110_1010, 0b1110_1010]);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let mask = Port::from(0b0000_1111);
+        assert_eq!(device.read_field(&mut i2c, mask, 0).unwrap(), 0x0A);
+        assert_eq!(
+            device
+                .read_field(&mut i2c, Port::from(0b0110_0000), 5)
+                .unwrap(),
+            0b11
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_write_field() {
+        let addr = Address::ADDR_0x20;
+        let expected = rmw(addr, Register::OUTPUT_PORT, 0b1010_1010, 0b1001_0110);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let written = device
+            .write_field(&mut i2c, Port::from(0b0011_1100), 2, 0x5)
+            .unwrap();
+        assert_eq!(written, Port::from(0b1001_0110));
+        i2c.done();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_port_bools_serde() {
+        let bools = PortBools::from(Port::P00 | Port::P06);
+        let json = serde_json::to_string(&bools).unwrap();
+        assert_eq!(json, "[true,false,false,false,false,false,true,false]");
+        let restored: PortBools = serde_json::from_str(&json).unwrap();
+        assert_eq!(Port::from(restored), Port::P00 | Port::P06);
+    }
+
+    #[test]
+    fn test_read_inputs_timeout() {
+        let addr = Address::ADDR_0x20;
+        let failed = || {
+            read_tx(addr, Register::INPUT_PORT, 0x00).with_error(MockError::Io(ErrorKind::Other))
+        };
+        let expected = [
+            failed(),
+            failed(),
+            read_tx(addr, Register::INPUT_PORT, 0x81),
+            failed(),
+            failed(),
+        ];
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut delay = LoggedDelay { log: log.clone() };
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let inputs = device
+            .read_inputs_timeout(&mut i2c, &mut delay, 2, 100)
+            .unwrap();
+        assert_eq!(inputs, Port::P00 | Port::P07);
+        assert_eq!(log.borrow().len(), 2);
+        assert_eq!(
+            device.read_inputs_timeout(&mut i2c, &mut delay, 1, 100),
+            Err(Error::Timeout)
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_ensure_outputs_already_matching() {
+        let addr = Address::ADDR_0x20;
+        let expected = [read_tx(addr, Register::OUTPUT_PORT, 0b0000_1111)];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert!(!device
+            .ensure_outputs(&mut i2c, Port::from(0b0000_1111))
+            .unwrap());
+        i2c.done();
+    }
+
+    #[test]
+    fn test_ensure_outputs_differing() {
+        let addr = Address::ADDR_0x20;
+        let expected = rmw(addr, Register::OUTPUT_PORT, 0b0000_1111, 0b1111_0000);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert!(device
+            .ensure_outputs(&mut i2c, Port::from(0b1111_0000))
+            .unwrap());
+        i2c.done();
+    }
+
+    #[test]
+    fn test_next_gray_changes_one_bit() {
+        let mut port = Port::empty();
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..256 {
+            let next = port.next_gray();
+            assert_eq!((port ^ next).bits.count_ones(), 1);
+            assert!(seen.insert(next.bits));
+            port = next;
+        }
+        assert_eq!(port, Port::empty());
+    }
+
+    #[test]
+    fn test_step_gray() {
+        let addr = Address::ADDR_0x20;
+        let mut expected = Vec::new();
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b0000_0001, 0b0000_0011));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b0000_0011, 0b0000_0010));
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device.step_gray(&mut i2c).unwrap();
+        device.step_gray(&mut i2c).unwrap();
+        i2c.done();
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_register_access_with_log() {
+        let addr = Address::ADDR_0x20;
+        let expected = rmw(addr, Register::OUTPUT_PORT, 0x0F, 0xF0);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device.invert_all_outputs(&mut i2c).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_read_inputs_all() {
+        let expected = [
+            read_tx(Address::ADDR_0x20, Register::INPUT_PORT, 0x11),
+            read_tx(Address::ADDR_0x23, Register::INPUT_PORT, 0x22),
+            read_tx(Address::ADDR_0x38, Register::INPUT_PORT, 0x33),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let addrs = [Address::ADDR_0x20, Address::ADDR_0x23, Address::ADDR_0x38];
+        let mut out = [Port::empty(); 3];
+        read_inputs_all(&mut i2c, &addrs, &mut out).unwrap();
+        assert_eq!(out, [Port::from(0x11), Port::from(0x22), Port::from(0x33)]);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_outputs_all_high() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::OUTPUT_PORT, 0xFF),
+            read_tx(addr, Register::OUTPUT_PORT, 0xF7),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert!(device.outputs_all_high(&mut i2c).unwrap());
+        assert!(!device.outputs_all_high(&mut i2c).unwrap());
+        i2c.done();
+    }
+
+    #[test]
+    fn test_outputs_all_low() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::OUTPUT_PORT, 0x00),
+            read_tx(addr, Register::OUTPUT_PORT, 0x08),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert!(device.outputs_all_low(&mut i2c).unwrap());
+        assert!(!device.outputs_all_low(&mut i2c).unwrap());
+        i2c.done();
+    }
+
+    #[test]
+    fn test_interrupt_config() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::INPUT_LATCH, 0b0000_0011),
+            read_tx(addr, Register::INTERRUPT_MASK, 0b1111_1100),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let (latch, mask) = device.interrupt_config(&mut i2c).unwrap();
+        assert_eq!(latch, Port::P00 | Port::P01);
+        assert_eq!(mask, Port::all() - Port::P00 - Port::P01);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_hold_pin_high() {
+        let addr = Address::ADDR_0x20;
+        let mut expected = vec![
+            read_tx(addr, Register::OUTPUT_PORT, 0b1000_0000),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b1000_0001]),
+        ];
+        expected.extend(input_reads(addr, &[0x00; 3]));
+        expected.push(Transaction::write(
+            addr as u8,
+            vec![Register::OUTPUT_PORT as u8, 0b1000_0000],
+        ));
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device.hold_pin_high(&mut i2c, Port::P00, 3).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_hold_pin_high_restores_after_read_error() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::OUTPUT_PORT, 0b1000_0000),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b1000_0001]),
+            read_tx(addr, Register::INPUT_PORT, 0x00),
+            read_tx(addr, Register::INPUT_PORT, 0x00).with_error(MockError::Io(ErrorKind::Other)),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b1000_0000]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let result = device.hold_pin_high(&mut i2c, Port::P00, 3);
+        assert_eq!(result, Err(MockError::Io(ErrorKind::Other)));
+        i2c.done();
+    }
+
+    #[cfg(feature = "test-helpers")]
+    #[test]
+    fn test_assert_outputs_match() {
+        let addr = Address::ADDR_0x20;
+        let expected = [read_tx(addr, Register::OUTPUT_PORT, 0b0000_0101)];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device
+            .assert_outputs(&mut i2c, Port::P00 | Port::P02)
+            .unwrap();
+        i2c.done();
+    }
+
+    #[cfg(feature = "test-helpers")]
+    #[test]
+    #[should_panic(expected = "expected 0b00000101 (P00 | P02), read 0b00000100 (P02)")]
+    fn test_assert_outputs_mismatch() {
+        let addr = Address::ADDR_0x20;
+        let expected = [read_tx(addr, Register::OUTPUT_PORT, 0b0000_0100)];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device
+            .assert_outputs(&mut i2c, Port::P00 | Port::P02)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_identify_pcal9554() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::OUTPUT_PORT_CONFIG, 0x00),
+            read_tx(addr, Register::POLARITY_INVERSION, 0b0000_0001),
+            Transaction::write(
+                addr as u8,
+                vec![Register::POLARITY_INVERSION as u8, 0b1111_1110],
+            ),
+            read_tx(addr, Register::POLARITY_INVERSION, 0b1111_1110),
+            Transaction::write(
+                addr as u8,
+                vec![Register::POLARITY_INVERSION as u8, 0b0000_0001],
+            ),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert!(device.identify(&mut i2c).unwrap());
+        i2c.done();
+    }
+
+    #[test]
+    fn test_identify_restores_polarity_after_read_error() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            read_tx(addr, Register::OUTPUT_PORT_CONFIG, 0x00),
+            read_tx(addr, Register::POLARITY_INVERSION, 0b0000_0001),
+            Transaction::write(
+                addr as u8,
+                vec![Register::POLARITY_INVERSION as u8, 0b1111_1110],
+            ),
+            read_tx(addr, Register::POLARITY_INVERSION, 0b1111_1110)
+                .with_error(MockError::Io(ErrorKind::Other)),
+            Transaction::write(
+                addr as u8,
+                vec![Register::POLARITY_INVERSION as u8, 0b0000_0001],
+            ),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(
+            device.identify(&mut i2c),
+            Err(MockError::Io(ErrorKind::Other))
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_identify_rejects_pcf8574() {
+        let addr = Address::ADDR_0x20;
+        // A PCF8574 ignores the register byte and returns its quasi-bidirectional pins, which
+        // idle high.
+        let expected = [read_tx(addr, Register::OUTPUT_PORT_CONFIG, 0xFF)];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert!(!device.identify(&mut i2c).unwrap());
+        i2c.done();
+    }
+
+    #[test]
+    fn test_prime_safe_outputs() {
+        let addr = Address::ADDR_0x20;
+        let expected = [Transaction::write(
+            addr as u8,
+            vec![Register::OUTPUT_PORT as u8, 0b0000_1111],
+        )];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device
+            .prime_safe_outputs(&mut i2c, Port::from(0b0000_1111))
+            .unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_edges_since() {
+        let addr = Address::ADDR_0x20;
+        let expected = input_reads(addr, &[0b0000_0110]);
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let (rising, falling) = device
+            .edges_since(&mut i2c, Port::from(0b0000_0011))
+            .unwrap();
+        assert_eq!(rising, Port::P02);
+        assert_eq!(falling, Port::P00);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_shift_outputs() {
+        let addr = Address::ADDR_0x20;
+        let mut expected = Vec::new();
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1000_0001, 0b0000_0010));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1000_0001, 0b0000_0011));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1000_0001, 0b0100_0000));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1000_0001, 0b1100_0000));
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(
+            device.shift_outputs(&mut i2c, true, false).unwrap(),
+            Port::P01
+        );
+        assert_eq!(
+            device.shift_outputs(&mut i2c, true, true).unwrap(),
+            Port::P00 | Port::P01
+        );
+        assert_eq!(
+            device.shift_outputs(&mut i2c, false, false).unwrap(),
+            Port::P06
+        );
+        assert_eq!(
+            device.shift_outputs(&mut i2c, false, true).unwrap(),
+            Port::P06 | Port::P07
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_latch_inputs_to_outputs() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::INPUT_PORT as u8],
+                vec![0b0011_0011],
+            ),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0011_0011]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        assert_eq!(
+            device.latch_inputs_to_outputs(&mut i2c).unwrap(),
+            Port::from(0b0011_0011)
+        );
+        i2c.done();
+    }
+
+    #[test]
+    fn test_copies_share_the_device() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0000_0001]),
+            Transaction::write_read(addr as u8, vec![Register::OUTPUT_PORT as u8], vec![0x01]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let copy = device;
+        device.write_outputs(&mut i2c, Port::P00).unwrap();
+        assert_eq!(copy.read_outputs(&mut i2c).unwrap(), Port::P00);
+        assert_eq!(copy.address(), device.address());
+        i2c.done();
+    }
 }
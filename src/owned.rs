@@ -0,0 +1,281 @@
+//! PCA9554 handle that owns the i2c bus.
+//!
+//! Unlike [`PCA9554`], this handle takes ownership of the bus and keeps an in-memory copy of the
+//! Output Port register.  This lets several output changes be made in RAM and written to the
+//! device in a single transaction.
+//!
+//! In shadow mode, `set_pin_high()`, `set_pin_low()` and `toggle()` only touch the in-memory copy
+//! and `commit()` writes it out once.  Outside shadow mode they perform a read-modify-write on the
+//! device.
+//!
+//! The Configuration register is cached the same way: `set_direction_shadow()` only touches the
+//! in-memory copy and `commit_config()` writes it out once.
+
+use crate::{Address, Port, PCA9554};
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+pub struct OwnedPCA9554<T> {
+    device: PCA9554<T>,
+    i2c: T,
+    outputs: Port,
+    config: Port,
+    shadow: bool,
+}
+
+impl<T, E> OwnedPCA9554<T>
+where
+    T: WriteRead<Error = E> + Write<Error = E>,
+{
+    /// Take ownership of the i2c bus.
+    ///
+    /// The output and configuration caches start out as `Port::all()`, which matches the power-on
+    /// default of the Output Port and Configuration registers.
+    pub fn new(i2c: T, address: Address) -> Self {
+        Self {
+            device: PCA9554::new(&i2c, address),
+            i2c,
+            outputs: Port::all(),
+            config: Port::all(),
+            shadow: false,
+        }
+    }
+
+    /// Give back the i2c bus.
+    pub fn release(self) -> T {
+        self.i2c
+    }
+
+    /// Same as `release()`.
+    pub fn into_inner(self) -> T {
+        self.i2c
+    }
+
+    /// Borrow the i2c bus.
+    pub fn inner(&self) -> &T {
+        &self.i2c
+    }
+
+    /// Borrow the i2c bus mutably, e.g. to talk to another device on it.  The output cache is not
+    /// affected.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.i2c
+    }
+
+    /// See [`PCA9554::address`].
+    pub fn address(&self) -> Option<Address> {
+        self.device.address()
+    }
+
+    /// The output state the driver believes the device currently holds.
+    pub fn cached_outputs(&self) -> Port {
+        self.outputs
+    }
+
+    /// Set pins high in the output cache only.  Nothing is written until `flush_outputs()`.
+    pub fn cache_set(&mut self, pins: Port) {
+        self.outputs.insert(pins);
+    }
+
+    /// Set pins low in the output cache only.  Nothing is written until `flush_outputs()`.
+    pub fn cache_clear(&mut self, pins: Port) {
+        self.outputs.remove(pins);
+    }
+
+    /// Write the cached output state to the Output Port register without reading it first.
+    pub fn flush_outputs(&mut self) -> Result<(), E> {
+        self.device.write_outputs(&mut self.i2c, self.outputs)
+    }
+
+    /// Enable or disable shadow mode.  Disabling shadow mode does not write pending changes, call
+    /// `commit()` first if they should be kept.
+    pub fn set_shadow(&mut self, enabled: bool) {
+        self.shadow = enabled;
+    }
+
+    pub fn is_shadow(&self) -> bool {
+        self.shadow
+    }
+
+    /// Write the shadowed Output Port register to the device.
+    ///
+    /// Equivalent to calling `OwnedPCA9554::flush_outputs()`.
+    pub fn commit(&mut self) -> Result<(), E> {
+        self.flush_outputs()
+    }
+
+    /// Drive the given pins high.
+    pub fn set_pin_high(&mut self, pins: Port) -> Result<(), E> {
+        self.update_outputs(|outputs| outputs.insert(pins))
+    }
+
+    /// Drive the given pins low.
+    pub fn set_pin_low(&mut self, pins: Port) -> Result<(), E> {
+        self.update_outputs(|outputs| outputs.remove(pins))
+    }
+
+    /// Invert the output state of the given pins.
+    pub fn toggle(&mut self, pins: Port) -> Result<(), E> {
+        self.update_outputs(|outputs| outputs.toggle(pins))
+    }
+
+    /// Apply `f` to the shadow in shadow mode, otherwise read-modify-write the Output Port register.
+    fn update_outputs<F>(&mut self, f: F) -> Result<(), E>
+    where
+        F: FnOnce(&mut Port),
+    {
+        if self.shadow {
+            f(&mut self.outputs);
+            return Ok(());
+        }
+        let mut outputs = self.read_outputs()?;
+        f(&mut outputs);
+        self.write_outputs(outputs)
+    }
+
+    /// See [`PCA9554::read_inputs`].
+    pub fn read_inputs(&mut self) -> Result<Port, E> {
+        self.device.read_inputs(&mut self.i2c)
+    }
+
+    /// Read the Output Port register and refresh the output cache with the result.
+    pub fn read_outputs(&mut self) -> Result<Port, E> {
+        let outputs = self.device.read_outputs(&mut self.i2c)?;
+        self.outputs = outputs;
+        Ok(outputs)
+    }
+
+    /// Write the Output Port register and update the output cache.
+    pub fn write_outputs(&mut self, output: Port) -> Result<(), E> {
+        self.device.write_outputs(&mut self.i2c, output)?;
+        self.outputs = output;
+        Ok(())
+    }
+
+    /// Write the Configuration register and update the configuration cache.
+    pub fn write_config(&mut self, config: Port) -> Result<(), E> {
+        self.device.write_config(&mut self.i2c, config)?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Read the Configuration register and refresh the configuration cache with the result.
+    pub fn read_config(&mut self) -> Result<Port, E> {
+        let config = self.device.read_config(&mut self.i2c)?;
+        self.config = config;
+        Ok(config)
+    }
+
+    /// The pin directions the driver believes the device currently holds, including uncommitted
+    /// changes.
+    pub fn cached_config(&self) -> Port {
+        self.config
+    }
+
+    /// Make `pins` inputs (`input` true) or outputs in the configuration cache only.  Nothing is
+    /// written until `commit_config()`.
+    pub fn set_direction_shadow(&mut self, pins: Port, input: bool) {
+        self.config.set(pins, input);
+    }
+
+    /// Write the cached pin directions to the Configuration register without reading it first.
+    pub fn commit_config(&mut self) -> Result<(), E> {
+        self.device.write_config(&mut self.i2c, self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Register;
+    use embedded_hal_mock::i2c::{Mock, Transaction};
+
+    #[test]
+    fn test_cache_then_flush() {
+        let addr = Address::ADDR_0x21;
+        let expected = [Transaction::write(
+            addr as u8,
+            vec![Register::OUTPUT_PORT as u8, 0b0000_0101],
+        )];
+
+        let mut device = OwnedPCA9554::new(Mock::new(&expected), addr);
+        device.cache_clear(Port::all());
+        device.cache_set(Port::P00 | Port::P01 | Port::P02);
+        device.cache_clear(Port::P01);
+        device.flush_outputs().unwrap();
+        assert_eq!(device.cached_outputs(), Port::P00 | Port::P02);
+        device.release().done();
+    }
+
+    #[test]
+    fn test_shadow_commit() {
+        let addr = Address::ADDR_0x20;
+        let expected = [Transaction::write(
+            addr as u8,
+            vec![Register::OUTPUT_PORT as u8, 0b1000_0110],
+        )];
+
+        let mut device = OwnedPCA9554::new(Mock::new(&expected), addr);
+        device.set_shadow(true);
+        device.set_pin_low(Port::all()).unwrap();
+        device.set_pin_high(Port::P00).unwrap();
+        device.set_pin_high(Port::P01).unwrap();
+        device.toggle(Port::P02).unwrap();
+        device.set_pin_low(Port::P00).unwrap();
+        device.toggle(Port::P07).unwrap();
+        device.set_pin_high(Port::P03).unwrap();
+        device.toggle(Port::P03).unwrap();
+        device.set_pin_high(Port::P05).unwrap();
+        device.set_pin_low(Port::P05).unwrap();
+        device.commit().unwrap();
+        assert_eq!(device.cached_outputs(), Port::P01 | Port::P02 | Port::P07);
+        device.release().done();
+    }
+
+    #[test]
+    fn test_set_pin_high_without_shadow() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::OUTPUT_PORT as u8],
+                vec![0b0000_0001],
+            ),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0001_0001]),
+        ];
+
+        let mut device = OwnedPCA9554::new(Mock::new(&expected), addr);
+        device.set_pin_high(Port::P04).unwrap();
+        device.release().done();
+    }
+
+    #[test]
+    fn test_inner_mut_shares_the_bus() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write(0x50, vec![0x00, 0x42]),
+            Transaction::write_read(addr as u8, vec![Register::INPUT_PORT as u8], vec![0x01]),
+        ];
+
+        let mut device = OwnedPCA9554::new(Mock::new(&expected), addr);
+        device.inner_mut().write(0x50, &[0x00, 0x42]).unwrap();
+        assert_eq!(device.read_inputs().unwrap(), Port::P00);
+        device.into_inner().done();
+    }
+
+    #[test]
+    fn test_shadowed_direction_changes() {
+        let addr = Address::ADDR_0x20;
+        let expected = [Transaction::write(
+            addr as u8,
+            vec![Register::CONFIG_PORT as u8, 0b1111_1010],
+        )];
+
+        let mut device = OwnedPCA9554::new(Mock::new(&expected), addr);
+        device.set_direction_shadow(Port::P00 | Port::P01, false);
+        device.set_direction_shadow(Port::P02, false);
+        device.set_direction_shadow(Port::P01, true);
+        assert_eq!(device.cached_config(), Port::from(0b1111_1010));
+        device.commit_config().unwrap();
+        device.release().done();
+    }
+}
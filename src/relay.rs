@@ -0,0 +1,91 @@
+//! Relay driver boards built around the expander.
+
+use crate::variant::Variant;
+use crate::{ActiveLevel, PinId, Port, PCA9554};
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// Treats the eight outputs of a [`PCA9554`] as relays.
+///
+/// The pins driving relays must already be configured as outputs.
+pub struct RelayBoard<T, V> {
+    device: PCA9554<T, V>,
+}
+
+impl<T, E, V> RelayBoard<T, V>
+where
+    T: WriteRead<Error = E> + Write<Error = E>,
+    V: Variant,
+{
+    /// Wrap `device`, whose relays are energized by driving their pin to `level`.
+    pub fn new(mut device: PCA9554<T, V>, level: ActiveLevel) -> Self {
+        device.set_active_level(level);
+        Self { device }
+    }
+
+    /// Give back the device handle.
+    pub fn release(self) -> PCA9554<T, V> {
+        self.device
+    }
+
+    /// Energize the relay on pin `relay`.  The other relays are left unchanged.
+    pub fn energize(&self, i2c: &mut T, relay: PinId) -> Result<(), E> {
+        self.device.set_pin_on(i2c, relay.to_port())
+    }
+
+    /// De-energize the relay on pin `relay`.  The other relays are left unchanged.
+    pub fn deenergize(&self, i2c: &mut T, relay: PinId) -> Result<(), E> {
+        self.device.set_pin_off(i2c, relay.to_port())
+    }
+
+    /// Energize every relay.
+    pub fn energize_all(&self, i2c: &mut T) -> Result<(), E> {
+        self.device.set_pin_on(i2c, Port::all())
+    }
+
+    /// De-energize every relay.
+    pub fn deenergize_all(&self, i2c: &mut T) -> Result<(), E> {
+        self.device.set_pin_off(i2c, Port::all())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::rmw;
+    use crate::{Address, Register};
+    use embedded_hal_mock::i2c::Mock;
+
+    #[test]
+    fn test_active_high_relays() {
+        let addr = Address::ADDR_0x20;
+        let mut expected = Vec::new();
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b0000_0000, 0b0000_0100));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b0000_0100, 0b0000_0000));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b0000_0000, 0b1111_1111));
+
+        let mut i2c = Mock::new(&expected);
+        let board = RelayBoard::new(PCA9554::new(&i2c, addr), ActiveLevel::High);
+        board.energize(&mut i2c, PinId::P2).unwrap();
+        board.deenergize(&mut i2c, PinId::P2).unwrap();
+        board.energize_all(&mut i2c).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_active_low_relays() {
+        let addr = Address::ADDR_0x20;
+        let mut expected = Vec::new();
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1111_1111, 0b1111_1101));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1111_1101, 0b1111_1111));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1111_1111, 0b0000_0000));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b0000_0000, 0b1111_1111));
+
+        let mut i2c = Mock::new(&expected);
+        let board = RelayBoard::new(PCA9554::new(&i2c, addr), ActiveLevel::Low);
+        board.energize(&mut i2c, PinId::P1).unwrap();
+        board.deenergize(&mut i2c, PinId::P1).unwrap();
+        board.energize_all(&mut i2c).unwrap();
+        board.deenergize_all(&mut i2c).unwrap();
+        i2c.done();
+    }
+}
@@ -0,0 +1,71 @@
+//! Translation between logical and physical pin numbers for boards with swapped pins.
+
+use crate::Port;
+
+/// A permutation of the eight pins.
+///
+/// Application code works with logical pins and `apply()` translates them to the physical pins
+/// written to the device; `reverse()` translates register values read back.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Remap {
+    // `table[logical]` is the physical pin index.
+    table: [u8; 8],
+}
+
+impl Remap {
+    /// Map logical pin `i` to physical pin `table[i]`.  Returns `None` unless `table` holds each
+    /// of the indices `0..=7` exactly once.
+    pub fn new(table: [u8; 8]) -> Option<Remap> {
+        let mut seen = 0u8;
+        for &physical in table.iter() {
+            if physical > 7 {
+                return None;
+            }
+            seen |= 1 << physical;
+        }
+        if seen != 0xFF {
+            return None;
+        }
+        Some(Remap { table })
+    }
+
+    /// Translate logical pins to physical pins.
+    pub fn apply(&self, logical: Port) -> Port {
+        let mut physical = Port::empty();
+        for (index, &target) in self.table.iter().enumerate() {
+            physical.set(Port::from(1 << target), logical.bits() & (1 << index) != 0);
+        }
+        physical
+    }
+
+    /// Translate physical pins back to logical pins.
+    pub fn reverse(&self, physical: Port) -> Port {
+        let mut logical = Port::empty();
+        for (index, &target) in self.table.iter().enumerate() {
+            logical.set(Port::from(1 << index), physical.bits() & (1 << target) != 0);
+        }
+        logical
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let remap = Remap::new([0, 4, 2, 3, 1, 5, 7, 6]).unwrap();
+        assert_eq!(remap.apply(Port::P01), Port::P04);
+        assert_eq!(remap.apply(Port::P04 | Port::P06), Port::P01 | Port::P07);
+        for bits in 0..=255u8 {
+            let logical = Port::from(bits);
+            assert_eq!(remap.reverse(remap.apply(logical)), logical);
+        }
+    }
+
+    #[test]
+    fn test_rejects_non_permutation() {
+        assert_eq!(Remap::new([0, 1, 2, 3, 4, 5, 6, 6]), None);
+        assert_eq!(Remap::new([0, 1, 2, 3, 4, 5, 6, 8]), None);
+    }
+}
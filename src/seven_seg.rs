@@ -0,0 +1,107 @@
+//! Seven-segment displays driven from the output pins.
+
+use crate::variant::Variant;
+use crate::{Port, PCA9554};
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// Segments `a` to `g`, bit 0 being `a`, for the hexadecimal digits 0–F.
+const PATTERNS: [u8; 16] = [
+    0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F, 0x77, 0x7C, 0x39, 0x5E, 0x79, 0x71,
+];
+
+/// Maps the segments of a seven-segment display to output pins.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SevenSeg {
+    segments: [Port; 7],
+    common_anode: bool,
+}
+
+impl SevenSeg {
+    /// `segments` holds the pin of each segment, `a` to `g`.  With a common anode a segment is lit
+    /// by driving its pin low, with a common cathode by driving it high.
+    pub const fn new(segments: [Port; 7], common_anode: bool) -> Self {
+        Self {
+            segments,
+            common_anode,
+        }
+    }
+
+    /// The segment pins to drive high to show `digit`.  Digits above 15 blank the display.
+    pub fn pattern(&self, digit: u8) -> Port {
+        let lit = PATTERNS.get(usize::from(digit)).copied().unwrap_or(0);
+        let mut high = Port::empty();
+        for (index, &pin) in self.segments.iter().enumerate() {
+            high.set(pin, (lit & (1 << index) != 0) != self.common_anode);
+        }
+        high
+    }
+
+    /// Show the hexadecimal `digit`.  Only the segment pins are written; digits above 15 blank
+    /// the display.
+    pub fn write_digit<T, E, V>(
+        &self,
+        i2c: &mut T,
+        device: &PCA9554<T, V>,
+        digit: u8,
+    ) -> Result<(), E>
+    where
+        T: WriteRead<Error = E> + Write<Error = E>,
+        V: Variant,
+    {
+        let mask = self
+            .segments
+            .iter()
+            .fold(Port::empty(), |mask, &pin| mask | pin);
+        let high = self.pattern(digit);
+        device.modify_outputs(i2c, |outputs| (outputs - mask) | high)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::rmw;
+    use crate::{Address, Register};
+    use embedded_hal_mock::i2c::Mock;
+
+    const SEGMENTS: [Port; 7] = [
+        Port::P00,
+        Port::P01,
+        Port::P02,
+        Port::P03,
+        Port::P04,
+        Port::P05,
+        Port::P06,
+    ];
+
+    #[test]
+    fn test_common_cathode() {
+        let addr = Address::ADDR_0x20;
+        let mut expected = Vec::new();
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1000_0000, 0b1111_1111));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1111_1111, 0b1000_0110));
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let display = SevenSeg::new(SEGMENTS, false);
+        display.write_digit(&mut i2c, &device, 8).unwrap();
+        display.write_digit(&mut i2c, &device, 1).unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_common_anode() {
+        let addr = Address::ADDR_0x20;
+        let mut expected = Vec::new();
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1111_1111, 0b1000_0000));
+        expected.extend(rmw(addr, Register::OUTPUT_PORT, 0b1000_0000, 0b1111_1001));
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let display = SevenSeg::new(SEGMENTS, true);
+        display.write_digit(&mut i2c, &device, 8).unwrap();
+        display.write_digit(&mut i2c, &device, 1).unwrap();
+        i2c.done();
+    }
+}
@@ -0,0 +1,112 @@
+//! Fluent staging of several output changes into a single write.
+
+use crate::variant::Variant;
+use crate::{Port, PCA9554};
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// Output changes staged by `PCA9554::stage()`.
+///
+/// The staged operations are applied in order on `commit()`, which reads the Output Port
+/// register once and writes it once.
+#[must_use = "staged changes are only written by commit()"]
+pub struct OutputStaging<'a, T, V> {
+    device: &'a PCA9554<T, V>,
+    // The staged operations fold into `outputs & keep ^ flip`.
+    keep: Port,
+    flip: Port,
+}
+
+impl<'a, T, E, V> OutputStaging<'a, T, V>
+where
+    T: WriteRead<Error = E> + Write<Error = E>,
+    V: Variant,
+{
+    pub(crate) fn new(device: &'a PCA9554<T, V>) -> Self {
+        Self {
+            device,
+            keep: Port::all(),
+            flip: Port::empty(),
+        }
+    }
+
+    /// Stage driving `pins` high.
+    pub fn set(mut self, pins: Port) -> Self {
+        self.keep.remove(pins);
+        self.flip.insert(pins);
+        self
+    }
+
+    /// Stage driving `pins` low.
+    pub fn clear(mut self, pins: Port) -> Self {
+        self.keep.remove(pins);
+        self.flip.remove(pins);
+        self
+    }
+
+    /// Stage inverting `pins`.
+    pub fn toggle(mut self, pins: Port) -> Self {
+        self.flip.toggle(pins);
+        self
+    }
+
+    /// Apply the staged changes and return the value written.
+    pub fn commit(self, i2c: &mut T) -> Result<Port, E> {
+        let (keep, flip) = (self.keep, self.flip);
+        self.device
+            .modify_outputs(i2c, |outputs| (outputs & keep) ^ flip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Address, Port, Register, PCA9554};
+    use embedded_hal_mock::i2c::{Mock, Transaction};
+
+    #[test]
+    fn test_stage_and_commit() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::OUTPUT_PORT as u8],
+                vec![0b1000_0000],
+            ),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b1000_0010]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        let result = device
+            .stage()
+            .set(Port::P00)
+            .toggle(Port::P00)
+            .set(Port::P01)
+            .commit(&mut i2c)
+            .unwrap();
+        assert_eq!(result, Port::P01 | Port::P07);
+        i2c.done();
+    }
+
+    #[test]
+    fn test_stage_clear_after_toggle() {
+        let addr = Address::ADDR_0x20;
+        let expected = [
+            Transaction::write_read(
+                addr as u8,
+                vec![Register::OUTPUT_PORT as u8],
+                vec![0b0000_0011],
+            ),
+            Transaction::write(addr as u8, vec![Register::OUTPUT_PORT as u8, 0b0000_0100]),
+        ];
+
+        let mut i2c = Mock::new(&expected);
+        let device = PCA9554::new(&i2c, addr);
+        device
+            .stage()
+            .toggle(Port::P00 | Port::P02)
+            .clear(Port::P00 | Port::P01)
+            .commit(&mut i2c)
+            .unwrap();
+        i2c.done();
+    }
+}
@@ -0,0 +1,47 @@
+//! Edge detection on the input pins.
+
+use crate::Port;
+
+/// Remembers the last input state and reports rising and falling edges against it.
+pub struct InputTracker {
+    last: Port,
+}
+
+impl InputTracker {
+    /// Start tracking from the input state `initial`.
+    pub fn new(initial: Port) -> Self {
+        Self { last: initial }
+    }
+
+    /// The input state recorded by the last `update()`.
+    pub fn last(&self) -> Port {
+        self.last
+    }
+
+    /// Record `current` and return `(rising, falling)`, the pins that went from low to high and
+    /// from high to low since the previous state.
+    pub fn update(&mut self, current: Port) -> (Port, Port) {
+        let rising = current - self.last;
+        let falling = self.last - current;
+        self.last = current;
+        (rising, falling)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update() {
+        let mut tracker = InputTracker::new(Port::P00 | Port::P01);
+        let (rising, falling) = tracker.update(Port::P01 | Port::P02);
+        assert_eq!(rising, Port::P02);
+        assert_eq!(falling, Port::P00);
+        assert_eq!(tracker.last(), Port::P01 | Port::P02);
+        assert_eq!(
+            tracker.update(Port::P01 | Port::P02),
+            (Port::empty(), Port::empty())
+        );
+    }
+}
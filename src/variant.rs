@@ -0,0 +1,70 @@
+//! Marker types selecting the exact part behind a [`PCA9554`](crate::PCA9554) handle.
+//!
+//! The PCA9554 and TCA9554A only implement the four core registers.  The PCAL9554B adds the
+//! extended registers at 0x40–0x4F (drive strength, input latch, pull resistors, interrupt mask
+//! and status, output port configuration).  Methods that need the extended registers are only
+//! available on handles for a variant implementing [`Extended`], and the register-level
+//! `read_register()`/`write_register()` return `Error::UnsupportedByVariant` when an extended
+//! register is accessed on a part without them.
+//!
+//! ```compile_fail
+//! # use embedded_hal_mock::i2c::Mock;
+//! use pca9554_rs::{variant, Address, Port, PCA9554};
+//!
+//! let mut i2c = Mock::new(&[]);
+//! let device = PCA9554::with_variant(&i2c, Address::ADDR_0x20, variant::Pca9554);
+//! device.watch_pins(&mut i2c, Port::P00);
+//! ```
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A supported part.
+pub trait Variant: sealed::Sealed {
+    /// Whether the part implements the extended registers at 0x40–0x4F.
+    const EXTENDED_REGISTERS: bool;
+}
+
+/// A part implementing the extended registers.
+pub trait Extended: Variant {}
+
+/// The PCAL9554B, with extended registers.  This is the default variant.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Pcal9554;
+
+/// The PCA9554, core registers only.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Pca9554;
+
+/// The TCA9554A, core registers only.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Tca9554a;
+
+impl sealed::Sealed for Pcal9554 {}
+impl sealed::Sealed for Pca9554 {}
+impl sealed::Sealed for Tca9554a {}
+
+impl Variant for Pcal9554 {
+    const EXTENDED_REGISTERS: bool = true;
+}
+
+impl Variant for Pca9554 {
+    const EXTENDED_REGISTERS: bool = false;
+}
+
+impl Variant for Tca9554a {
+    const EXTENDED_REGISTERS: bool = false;
+}
+
+impl Extended for Pcal9554 {}
+
+/// The address range a part answers on, as a runtime value for code that only learns the part at
+/// run time, e.g. from a bus scan.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Family {
+    /// The PCA9554 and PCAL9554B, at 0x20–0x27.
+    Pca9554,
+    /// The PCA9554A and TCA9554A, at 0x38–0x3F.
+    Pca9554a,
+}